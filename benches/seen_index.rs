@@ -0,0 +1,135 @@
+//! Measures the memory and the throughput of [PaymentEngine::process] on a synthetic transaction file, with and without a [SeenTxIndex].
+//!
+//! The file is generated once into the target directory, e.g. `target/seen_index-10000000.csv`. It mimics a transaction file of 10k clients:
+//! increasing tx ids, mostly deposits, every 4th record a withdrawal and every 16th record a dispute of a recent transaction.
+//! The number of rows is configured by the `ROWS` environment variable (default: 10 million) and the retention of the transaction repository
+//! by `RETAIN_COUNT` (default: unbounded, like `--retain-count`), e.g.:
+//!
+//! ```sh
+//! ROWS=100000000 RETAIN_COUNT=1000000 cargo bench --bench seen_index
+//! ```
+//!
+//! Every configuration is run in its own process, thus the reported peak RSS covers the whole engine, including the decoder and the repositories.
+//! The peak RSS is read from `/proc/self/status`, thus it is only reported on Linux.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Instant;
+
+use toy_payment_engine::prelude::{
+    BitmapSeenIndex, CsvDecoder, InMemoryAccountRepository, InMemoryTxRepository, PaymentEngine,
+    RoaringSeenIndex, SeenTxIndex, TxRetention,
+};
+
+const CLIENTS: u64 = 10_000;
+
+/// Set for the process of a single configuration, see [run_configuration]
+const CONFIGURATION: &str = "SEEN_INDEX_BENCH_CONFIGURATION";
+
+fn main() {
+    let rows: u64 = env_var("ROWS").unwrap_or(10_000_000);
+    let retention = match env_var("RETAIN_COUNT") {
+        Some(count) => TxRetention::Count(count as usize),
+        None => TxRetention::Unbounded,
+    };
+    let file = synthetic_file(rows);
+
+    if let Ok(seen_index) = env::var(CONFIGURATION) {
+        run_configuration(&file, &seen_index, retention, rows);
+        return;
+    }
+
+    println!("rows: {rows}, retention: {retention:?}");
+    println!(
+        "{:<10} {:>14} {:>16} {:>16}",
+        "seen index", "peak RSS (MiB)", "index (MiB)", "rows per second"
+    );
+    for seen_index in ["none", "bitmap", "roaring"] {
+        let status = Command::new(env::current_exe().expect("Failed to locate the benchmark"))
+            .env(CONFIGURATION, seen_index)
+            .status()
+            .expect("Failed to run the configuration");
+        assert!(status.success(), "The configuration {seen_index} failed");
+    }
+}
+
+fn env_var(name: &str) -> Option<u64> {
+    env::var(name).ok().and_then(|value| value.parse().ok())
+}
+
+/// Generates the file, unless it already exists
+fn synthetic_file(rows: u64) -> PathBuf {
+    let target = PathBuf::from(env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".into()));
+    let path = target.join(format!("seen_index-{rows}.csv"));
+    if path.exists() {
+        return path;
+    }
+
+    let partial = path.with_extension("partial");
+    let mut sink = BufWriter::new(File::create(&partial).expect("Failed to create the file"));
+    writeln!(sink, "type,client,tx,amount").unwrap();
+    for row in 0..rows {
+        let tx = row + 1;
+        let client = row % CLIENTS + 1;
+        match row % 16 {
+            // The deposit or withdrawal 8 rows before
+            15 => writeln!(sink, "dispute,{},{}", (row - 8) % CLIENTS + 1, tx - 8),
+            3 | 7 | 11 => writeln!(sink, "withdrawal,{client},{tx},1"),
+            _ => writeln!(sink, "deposit,{client},{tx},2.5"),
+        }
+        .expect("Failed to write the file");
+    }
+    sink.flush().expect("Failed to write the file");
+    fs::rename(&partial, &path).expect("Failed to move the file");
+
+    path
+}
+
+fn run_configuration(file: &Path, name: &str, retention: TxRetention, rows: u64) {
+    let seen_index: Option<Arc<dyn SeenTxIndex>> = match name {
+        "bitmap" => Some(Arc::new(BitmapSeenIndex::new())),
+        "roaring" => Some(Arc::new(RoaringSeenIndex::new())),
+        _ => None,
+    };
+    let mut builder = PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::with_retention(retention)),
+    );
+    if let Some(seen_index) = &seen_index {
+        builder = builder.seen_index(Arc::clone(seen_index));
+    }
+    let engine = builder.build();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("Failed to build the runtime");
+    let mut decoder = CsvDecoder::new(File::open(file).expect("Failed to open the file"));
+
+    let start = Instant::now();
+    runtime
+        .block_on(engine.process(decoder.decode_tx()))
+        .expect("Failed to process the file");
+    let secs = start.elapsed().as_secs_f64();
+
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    let peak_rss = peak_rss_bytes().map_or("n/a".to_owned(), |bytes| format!("{:.0}", mib(bytes)));
+    let index = seen_index.map_or("-".to_owned(), |index| {
+        format!("{:.1}", mib(index.memory_bytes()))
+    });
+    let throughput = rows as f64 / secs;
+
+    println!("{name:<10} {peak_rss:>14} {index:>16} {throughput:>16.0}");
+}
+
+/// The high water mark of the resident set of this process
+fn peak_rss_bytes() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(kib * 1024)
+}
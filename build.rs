@@ -0,0 +1,13 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The vendored protoc makes the build independent of a locally installed protobuf compiler
+    let mut config = prost_build::Config::new();
+    config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_prost_build::configure().compile_with_config(
+        config,
+        &["proto/payment_engine.proto"],
+        &["proto"],
+    )?;
+
+    Ok(())
+}
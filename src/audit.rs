@@ -0,0 +1,236 @@
+use std::io::{BufRead, Write};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::models::account::Account;
+use crate::models::transaction::TxRecord;
+
+/// The `prev` hash of the first entry.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Receives every successfully applied record, together with the resulting account of the client.
+pub trait AuditSink: Send + Sync {
+    fn append(&self, record: TxRecord, account: Option<Account>) -> Result<()>;
+
+    fn flush(&self) -> Result<()>;
+}
+
+/// A tamper-evident audit log, written as JSON Lines.
+///
+/// Each line contains the sequence number, the record, the resulting account, the hash of the previous line (`prev`) and its own `hash`.
+/// The hash is the SHA-256 of the line without the `hash` field, thus changing, removing or reordering any line breaks the chain, see [verify].
+///
+/// The lines are written in their canonical form, i.e. with sorted keys and without whitespace. This way, the verification can recompute the hash from the parsed line.
+pub struct AuditLog<W> {
+    state: Mutex<State<W>>,
+}
+
+struct State<W> {
+    writer: W,
+    seq: u64,
+    prev: String,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    seq: u64,
+    record: TxRecord,
+    account: Option<Account>,
+    prev: &'a str,
+}
+
+impl<W: Write> AuditLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            state: Mutex::new(State {
+                writer,
+                seq: 0,
+                prev: GENESIS_HASH.to_owned(),
+            }),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.state
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .writer
+    }
+}
+
+impl<W: Write + Send> AuditSink for AuditLog<W> {
+    fn append(&self, record: TxRecord, account: Option<Account>) -> Result<()> {
+        // A poisoned lock only means, that another append panicked. The chain itself is still consistent.
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let entry = AuditEntry {
+            seq: state.seq + 1,
+            record,
+            account,
+            prev: &state.prev,
+        };
+        // Converting into a value sorts the keys, which makes the serialization canonical
+        let mut line =
+            serde_json::to_value(&entry).context("Failed to serialize the audit entry")?;
+        let hash = hash(&line);
+        line["hash"] = Value::String(hash.clone());
+
+        serde_json::to_writer(&mut state.writer, &line)
+            .context("Failed to write the audit entry")?;
+        state
+            .writer
+            .write_all(b"\n")
+            .context("Failed to write the line break")?;
+
+        state.seq += 1;
+        state.prev = hash;
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        state
+            .writer
+            .flush()
+            .context("Failed to flush the audit log")
+    }
+}
+
+fn hash(line: &Value) -> String {
+    let digest = Sha256::digest(line.to_string().as_bytes());
+
+    format!("{digest:x}")
+}
+
+/// The result of [verify]. A broken link is not an error, but the expected finding for a tampered log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Intact { entries: u64 },
+    Broken { line: u64, reason: String },
+}
+
+/// Walks the chain of an [AuditLog] and reports the first broken link.
+///
+/// Errors are only returned, if the log can't be read at all.
+pub fn verify<R: BufRead>(reader: R) -> Result<Verification> {
+    let mut prev = GENESIS_HASH.to_owned();
+    let mut entries = 0;
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line_no = idx as u64 + 1;
+        let line =
+            line.with_context(|| format!("Failed to read line {line_no} of the audit log"))?;
+
+        match verify_line(&line, &prev, line_no) {
+            Ok(hash) => prev = hash,
+            Err(reason) => {
+                return Ok(Verification::Broken {
+                    line: line_no,
+                    reason,
+                });
+            }
+        }
+
+        entries += 1;
+    }
+
+    Ok(Verification::Intact { entries })
+}
+
+/// Returns the hash of the verified line, which has to be the `prev` of the next line.
+fn verify_line(line: &str, prev: &str, expected_seq: u64) -> Result<String, String> {
+    let mut value: Value =
+        serde_json::from_str(line).map_err(|err| format!("malformed entry: {err}"))?;
+    let Some(Value::String(stored)) = value.as_object_mut().and_then(|obj| obj.remove("hash"))
+    else {
+        return Err("the entry has no hash".to_owned());
+    };
+
+    if value["seq"].as_u64() != Some(expected_seq) {
+        return Err(format!(
+            "expected seq {expected_seq}, but found {}",
+            value["seq"]
+        ));
+    }
+    if value["prev"].as_str() != Some(prev) {
+        return Err("the prev hash doesn't match the hash of the previous entry".to_owned());
+    }
+    if hash(&value) != stored {
+        return Err("the hash doesn't match the content of the entry".to_owned());
+    }
+
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use claims::assert_ok;
+
+    use super::*;
+    use crate::models::NonNegativeDecimal;
+    use crate::models::client::ClientId;
+    use crate::models::transaction::{Deposit, TransactionId};
+
+    fn write_log(count: u32) -> String {
+        let log = AuditLog::new(Vec::new());
+
+        for id in 1..=count {
+            let record = TxRecord::from(Deposit {
+                client_id: ClientId::new(1),
+                tx_id: TransactionId::new(id),
+                amount: NonNegativeDecimal::try_from(id).unwrap(),
+            });
+            assert_ok!(log.append(record, Some(Account::new(ClientId::new(1)))));
+        }
+
+        String::from_utf8(log.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn untouched_log_is_intact() {
+        let log = write_log(3);
+
+        let verification = assert_ok!(verify(Cursor::new(log)));
+
+        assert_eq!(verification, Verification::Intact { entries: 3 });
+    }
+
+    #[test]
+    fn changed_entry_breaks_the_chain() {
+        let log = write_log(3).replacen(r#""amount":"2""#, r#""amount":"20""#, 1);
+
+        let verification = assert_ok!(verify(Cursor::new(log)));
+
+        assert_eq!(
+            verification,
+            Verification::Broken {
+                line: 2,
+                reason: "the hash doesn't match the content of the entry".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn removed_entry_breaks_the_chain() {
+        let log: Vec<_> = write_log(3).lines().map(str::to_owned).collect();
+        let log = [log[0].as_str(), log[2].as_str()].join("\n");
+
+        let verification = assert_ok!(verify(Cursor::new(log)));
+
+        assert_eq!(
+            verification,
+            Verification::Broken {
+                line: 2,
+                reason: "expected seq 2, but found 3".to_owned()
+            }
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tracing::{error, warn};
+
+use crate::config::FailureMode;
+use crate::engine::{PaymentEngine, RecordOutcome};
+use crate::models::rejection::Rejection;
+use crate::models::transaction::TxRecord;
+use crate::repository::account::AccountRepository;
+use crate::repository::transaction::TransactionRepository;
+use crate::socket::LineFormat;
+
+/// A message of a topic partition, as delivered by a log-based message bus like Kafka.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub offset: u64,
+    pub key: Option<Vec<u8>>,
+    pub payload: Vec<u8>,
+}
+
+/// Reads a single topic partition on behalf of a consumer group, e.g. backed by a Kafka consumer assigned to the partition.
+///
+/// The committed offset follows the Kafka semantics: it is the offset of the next message to read, thus a restarted consumer continues right after the last committed message.
+#[async_trait]
+pub trait PartitionConsumer: Send {
+    /// Returns the next message, or `None` at the end of the partition.
+    async fn poll(&mut self) -> Result<Option<Message>>;
+
+    /// Commits the offset of the next message to read for the consumer group.
+    async fn commit(&mut self, next_offset: u64) -> Result<()>;
+}
+
+/// Appends messages to a topic, e.g. backed by a Kafka producer. Returns the offset of the appended message.
+#[async_trait]
+pub trait Producer: Send + Sync {
+    async fn send(&self, key: Option<Vec<u8>>, payload: Vec<u8>) -> Result<u64>;
+}
+
+/// Processes a topic partition record by record and commits the offset of a record, once the engine decided about it.
+///
+/// An applied or rejected record is committed, as the engine wouldn't decide differently on a redelivery. A malformed message is logged and committed as well, like a malformed line of the CSV input.
+/// A failed record, e.g. due to an error of a repository, is never committed. Because the committed offset covers all messages before it, the processing stops at the failed record and returns its error,
+/// regardless of the [FailureMode]. With [FailureMode::Strict] a rejected record stops the processing the same way, like in [PaymentEngine::process]. A restarted consumer of the group continues at this record.
+///
+/// Thus the delivery is at least once: a crash between applying a record and committing it redelivers the record. It is only rejected as duplicate, if the repositories of the engine survived the crash.
+/// With the in-memory repositories, a restarted engine starts empty, thus it has to replay the partition from the beginning instead.
+///
+/// The records are observed by the [crate::prelude::RunStats] of the engine, like a run of [PaymentEngine::process]. A message has no line number, thus an abort is only reported with the offset in its error.
+pub async fn process_partition<AR, TR, C>(
+    engine: &PaymentEngine<AR, TR>,
+    mut consumer: C,
+    format: LineFormat,
+) -> Result<()>
+where
+    AR: AccountRepository,
+    TR: TransactionRepository,
+    C: PartitionConsumer,
+{
+    engine.start_run().await?;
+
+    while let Some(message) = consumer.poll().await.context("Failed to poll a message")? {
+        let next_offset = message.offset + 1;
+        let record = std::str::from_utf8(&message.payload)
+            .context("Failed to decode the payload as UTF-8")
+            .and_then(|line| format.decode(line.trim()));
+
+        match record {
+            Ok(record) => {
+                if let Err(err) = engine.process_record(record).await {
+                    let aborts = Rejection::of(&err).is_none()
+                        || engine.config().failure_mode == FailureMode::Strict;
+                    match aborts {
+                        true => {
+                            let err = err.context(format!(
+                                "Aborted the partition at offset: {}, because the record failed",
+                                message.offset
+                            ));
+                            engine.abort_run(None, &err);
+                            return Err(err);
+                        }
+                        false => error!("Failed to process TxRecord: {err:?}"),
+                    }
+                }
+            }
+            Err(err) => warn!(
+                "Skipped the malformed message at offset: {}: {err:#}",
+                message.offset
+            ),
+        }
+
+        consumer
+            .commit(next_offset)
+            .await
+            .with_context(|| format!("Failed to commit the offset: {next_offset}"))?;
+    }
+
+    Ok(())
+}
+
+/// Publishes the outcome of every record as JSON, e.g. `{"record":{...},"outcome":"applied","account":{...}}`, keyed by the client id.
+///
+/// Keying by the client keeps the outcomes of a client in order, when the output topic has several partitions.
+pub struct OutcomePublisher<P> {
+    producer: P,
+}
+
+#[derive(Serialize)]
+struct OutcomeMessage<'a> {
+    record: &'a TxRecord,
+    #[serde(flatten)]
+    outcome: &'a RecordOutcome,
+}
+
+impl<P> OutcomePublisher<P>
+where
+    P: Producer,
+{
+    pub fn new(producer: P) -> Self {
+        Self { producer }
+    }
+
+    /// Returns the offset of the published message
+    pub async fn publish(&self, record: &TxRecord, outcome: &RecordOutcome) -> Result<u64> {
+        let payload = serde_json::to_vec(&OutcomeMessage { record, outcome })
+            .context("Failed to serialize the outcome")?;
+        let key = record.client_id().into_inner().to_string().into_bytes();
+
+        self.producer
+            .send(Some(key), payload)
+            .await
+            .context("Failed to publish the outcome")
+    }
+}
+
+/// An in-process stand-in for a broker, e.g. for tests without network. Topics and partitions are created on first use.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryBroker {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    partitions: HashMap<TopicPartition, Vec<Message>>,
+    // The committed offsets by consumer group
+    commits: HashMap<(String, TopicPartition), u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TopicPartition {
+    topic: String,
+    partition: u32,
+}
+
+impl TopicPartition {
+    fn new(topic: &str, partition: u32) -> Self {
+        Self {
+            topic: topic.to_owned(),
+            partition,
+        }
+    }
+}
+
+impl InMemoryBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A producer appending to the given partition
+    pub fn producer(&self, topic: &str, partition: u32) -> InMemoryProducer {
+        InMemoryProducer {
+            inner: Arc::clone(&self.inner),
+            partition: TopicPartition::new(topic, partition),
+        }
+    }
+
+    /// A consumer of the given partition, that starts at the committed offset of the group
+    pub async fn consumer(&self, group: &str, topic: &str, partition: u32) -> InMemoryConsumer {
+        let partition = TopicPartition::new(topic, partition);
+        let position = self.committed_offset(group, &partition).await.unwrap_or(0);
+
+        InMemoryConsumer {
+            inner: Arc::clone(&self.inner),
+            group: group.to_owned(),
+            partition,
+            position,
+        }
+    }
+
+    /// The committed offset of the group, i.e. the offset of the next message to read
+    pub async fn committed(&self, group: &str, topic: &str, partition: u32) -> Option<u64> {
+        self.committed_offset(group, &TopicPartition::new(topic, partition))
+            .await
+    }
+
+    pub async fn messages(&self, topic: &str, partition: u32) -> Vec<Message> {
+        let guard = self.inner.read().await;
+
+        guard
+            .partitions
+            .get(&TopicPartition::new(topic, partition))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn committed_offset(&self, group: &str, partition: &TopicPartition) -> Option<u64> {
+        let guard = self.inner.read().await;
+
+        guard
+            .commits
+            .get(&(group.to_owned(), partition.clone()))
+            .copied()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InMemoryProducer {
+    inner: Arc<RwLock<Inner>>,
+    partition: TopicPartition,
+}
+
+#[async_trait]
+impl Producer for InMemoryProducer {
+    async fn send(&self, key: Option<Vec<u8>>, payload: Vec<u8>) -> Result<u64> {
+        let mut guard = self.inner.write().await;
+
+        let messages = guard.partitions.entry(self.partition.clone()).or_default();
+        let offset = messages.len() as u64;
+        messages.push(Message {
+            offset,
+            key,
+            payload,
+        });
+
+        Ok(offset)
+    }
+}
+
+#[derive(Debug)]
+pub struct InMemoryConsumer {
+    inner: Arc<RwLock<Inner>>,
+    group: String,
+    partition: TopicPartition,
+    position: u64,
+}
+
+#[async_trait]
+impl PartitionConsumer for InMemoryConsumer {
+    async fn poll(&mut self) -> Result<Option<Message>> {
+        let guard = self.inner.read().await;
+
+        let message = guard
+            .partitions
+            .get(&self.partition)
+            .and_then(|messages| messages.get(self.position as usize))
+            .cloned();
+        if message.is_some() {
+            self.position += 1;
+        }
+
+        Ok(message)
+    }
+
+    async fn commit(&mut self, next_offset: u64) -> Result<()> {
+        let mut guard = self.inner.write().await;
+
+        guard
+            .commits
+            .insert((self.group.clone(), self.partition.clone()), next_offset);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_ok, assert_ok_eq, assert_some_eq};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn consumer_continues_at_the_committed_offset() {
+        let broker = InMemoryBroker::new();
+        let producer = broker.producer("tx", 0);
+        for payload in ["a", "b", "c"] {
+            assert_ok!(producer.send(None, payload.into()).await);
+        }
+        let mut consumer = broker.consumer("engine", "tx", 0).await;
+        assert_ok!(consumer.poll().await);
+        assert_ok!(consumer.commit(1).await);
+
+        let mut restarted = broker.consumer("engine", "tx", 0).await;
+
+        let message = assert_ok!(restarted.poll().await);
+        assert_some_eq!(message.map(|m| m.offset), 1);
+        assert_some_eq!(broker.committed("engine", "tx", 0).await, 1);
+        assert_eq!(broker.committed("other", "tx", 0).await, None);
+    }
+
+    #[tokio::test]
+    async fn empty_partition_has_no_messages() {
+        let broker = InMemoryBroker::new();
+        let mut consumer = broker.consumer("engine", "tx", 0).await;
+
+        assert_ok_eq!(consumer.poll().await, None);
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// The [EngineConfig] bundles the business rules of the [crate::engine::PaymentEngine].
+///
+/// Different business units run the same binary with different rules. Every field has a default, that reflects the behavior of the engine before it became configurable.
+/// This way, a missing or partial configuration file never changes the semantics silently.
+///
+/// The configuration can be loaded from a TOML file, e.g.:
+///
+/// ```toml
+/// withdrawal_opens_account = false
+/// locked_account = "freeze"
+/// dispute = "deposits_only"
+/// precision = 4
+/// max_amount = "1000000"
+/// failure_mode = "strict"
+/// idempotency = "reject_any_reuse"
+///
+/// [limits.withdrawal]
+/// max_amount = "500"
+/// daily_amount = "2000"
+/// velocity = { count = 5, window_secs = 60 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Whether a withdrawal for an unknown client opens a new (empty) account.
+    pub withdrawal_opens_account: bool,
+
+    /// The behavior for records referencing an account, that has been locked by a chargeback.
+    pub locked_account: LockedAccountPolicy,
+
+    /// Which kind of transactions may be disputed.
+    pub dispute: DisputePolicy,
+
+    /// The maximum number of decimal places accepted for an amount. Records exceeding it are rejected.
+    pub precision: Option<u32>,
+
+    /// The maximum amount of a single deposit or withdrawal. Records exceeding it are rejected.
+    pub max_amount: Option<Decimal>,
+
+    /// How the engine reacts to a record, that could not be processed.
+    pub failure_mode: FailureMode,
+
+    /// How the engine reacts to a deposit or withdrawal reusing the tx id of an already stored transaction.
+    pub idempotency: IdempotencyPolicy,
+
+    /// The limits per client, see [LimitsConfig].
+    pub limits: LimitsConfig,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            withdrawal_opens_account: false,
+            locked_account: LockedAccountPolicy::Ignore,
+            dispute: DisputePolicy::DepositsAndWithdrawals,
+            precision: None,
+            max_amount: None,
+            failure_mode: FailureMode::Lenient,
+            idempotency: IdempotencyPolicy::RetryFailed,
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        toml::from_str(toml).context("Failed to parse the engine configuration as TOML")
+    }
+
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the engine configuration from: {path:?}"))?;
+
+        Self::from_toml_str(&content)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockedAccountPolicy {
+    /// The lock flag is reported, but does not restrict any further records.
+    #[default]
+    Ignore,
+    /// Withdrawals from a locked account are rejected. Everything else is still processed.
+    BlockWithdrawals,
+    /// Every record referencing a locked account is rejected.
+    Freeze,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputePolicy {
+    /// Deposits and withdrawals can be disputed. See [crate::models::account::Direction] for the semantics.
+    #[default]
+    DepositsAndWithdrawals,
+    /// Only deposits can be disputed. A dispute referencing a withdrawal is rejected.
+    DepositsOnly,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureMode {
+    /// A failing record is logged and skipped, the processing continues with the next record.
+    #[default]
+    Lenient,
+    /// The processing stops at the first failing record and the error is returned to the caller.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdempotencyPolicy {
+    /// The tx id of a failed transaction may be reused by a retry of the same client, replacing the stored failed transaction.
+    #[default]
+    RetryFailed,
+    /// Any reuse of a tx id is rejected as a duplicate, even if the stored transaction failed.
+    RejectAnyReuse,
+}
+
+/// The limits of a client, evaluated before a deposit or withdrawal is applied. A record exceeding any of them is rejected with [crate::models::rejection::Rejection::LimitExceeded].
+///
+/// The records don't carry a timestamp, thus the windows are measured by the clock of the engine, i.e. since a record has been applied.
+/// In a batch run, this is the processing time, not the time of the payment: a whole file is processed within seconds, thus all of its records
+/// count towards the same day and, usually, the same velocity window. The windows are only meaningful for a long running engine, e.g. `serve` or `listen`.
+/// Only applied records count towards the limits. Without any limit, nothing is tracked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub deposit: TxLimits,
+    pub withdrawal: TxLimits,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TxLimits {
+    /// The maximum amount of a single record of the client.
+    pub max_amount: Option<Decimal>,
+
+    /// The maximum sum of the amounts of a client within the last 24 hours, including the record.
+    pub daily_amount: Option<Decimal>,
+
+    /// The maximum number of records of a client within a sliding window, including the record.
+    pub velocity: Option<Velocity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Velocity {
+    pub count: usize,
+    pub window_secs: u64,
+}
+
+impl TxLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_amount.is_none() && self.daily_amount.is_none() && self.velocity.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use rust_decimal::dec;
+
+    use super::*;
+
+    #[test]
+    fn empty_toml_yields_the_default_config() {
+        let config = assert_ok!(EngineConfig::from_toml_str(""));
+
+        assert_eq!(config, EngineConfig::default());
+    }
+
+    #[test]
+    fn can_parse_all_options() {
+        let toml = r#"
+            withdrawal_opens_account = true
+            locked_account = "freeze"
+            dispute = "deposits_only"
+            precision = 4
+            max_amount = "1000.5"
+            failure_mode = "strict"
+            idempotency = "reject_any_reuse"
+
+            [limits.deposit]
+            velocity = { count = 3, window_secs = 60 }
+
+            [limits.withdrawal]
+            max_amount = "500"
+            daily_amount = "2000"
+        "#;
+
+        let config = assert_ok!(EngineConfig::from_toml_str(toml));
+
+        assert_eq!(
+            config,
+            EngineConfig {
+                withdrawal_opens_account: true,
+                locked_account: LockedAccountPolicy::Freeze,
+                dispute: DisputePolicy::DepositsOnly,
+                precision: Some(4),
+                max_amount: Some(dec!(1000.5)),
+                failure_mode: FailureMode::Strict,
+                idempotency: IdempotencyPolicy::RejectAnyReuse,
+                limits: LimitsConfig {
+                    deposit: TxLimits {
+                        velocity: Some(Velocity {
+                            count: 3,
+                            window_secs: 60,
+                        }),
+                        ..TxLimits::default()
+                    },
+                    withdrawal: TxLimits {
+                        max_amount: Some(dec!(500)),
+                        daily_amount: Some(dec!(2000)),
+                        velocity: None,
+                    },
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        let res = EngineConfig::from_toml_str("unknown = true");
+
+        assert_err!(res, "Expected an unknown option to be rejected");
+    }
+}
@@ -2,16 +2,19 @@ use std::io::{Read, Write};
 
 use anyhow::{Context, Result, anyhow};
 use csv::{Reader, ReaderBuilder, Trim};
-use futures::stream::{self, FusedStream, StreamExt};
-use serde::Deserialize;
+use futures::stream::{self, FusedStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::pin;
 use tracing::error;
 
 use crate::models::NonNegativeDecimal;
 use crate::models::account::Account;
 use crate::models::client::ClientId;
+use crate::models::journal::TrialBalance;
 use crate::models::transaction::{
-    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+    Chargeback, Deposit, Dispute, Resolve, Transaction, TransactionId, TxRecord, Withdrawal,
 };
+use crate::reconcile::Reconciliation;
 
 /// The CsvDecoder plays an important role in the system design.
 ///
@@ -27,6 +30,14 @@ use crate::models::transaction::{
 /// I decided to use a fused stream to avoid any undesired undefined behavior, if an consumer calls next, after a `None` has been received. See the documentation for the [StreamExt::fuse] method
 pub struct CsvDecoder<R> {
     reader: Reader<R>,
+    stats: DecodeStats,
+}
+
+/// The number of rows read and decoded by [CsvDecoder::decode_tx] so far. The difference are the skipped, malformed rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    pub read: u64,
+    pub decoded: u64,
 }
 
 impl<R: Read> CsvDecoder<R> {
@@ -41,39 +52,156 @@ impl<R: Read> CsvDecoder<R> {
             .delimiter(b',')
             .from_reader(reader);
 
-        Self { reader }
+        Self {
+            reader,
+            stats: DecodeStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> DecodeStats {
+        self.stats
     }
 
     pub fn decode_tx(&mut self) -> impl FusedStream<Item = TxRecord> {
-        let records = self
+        self.decode_numbered_tx().map(|(_line, tx)| tx)
+    }
+
+    /// Like [CsvDecoder::decode_tx], but every record is paired with its line number in the input, counted from 1 including the header.
+    pub fn decode_numbered_tx(&mut self) -> impl FusedStream<Item = (u64, TxRecord)> {
+        let headers = self
             .reader
-            .deserialize::<DeTxRecord>()
-            .map(|rec| rec.context("Failed to deserialize CSV record into DeTxRecord"))
-            .filter_map(|deserialized| {
-                deserialized
-                    .inspect_err(|err| {
-                        error!("Failed to deserialize DeTxRecord from CsvRecord: {err:?}");
-                    })
-                    .and_then(TxRecord::try_from)
-                    .inspect_err(|err| {
-                        tracing::error!("Failed to convert DeTxRecord to TxRecord: {err:?}");
-                    })
-                    .ok()
-            });
+            .headers()
+            .cloned()
+            .inspect_err(|err| error!("Failed to read the CSV header: {err:?}"))
+            .ok();
+
+        let stats = &mut self.stats;
+        let records = self.reader.records().filter_map(move |record| {
+            stats.read += 1;
+            let record = record
+                .inspect_err(|err| error!("Failed to read CsvRecord: {err:?}"))
+                .ok()?;
+            let line = record.position().map_or(0, |pos| pos.line());
+
+            let tx = record
+                .deserialize::<DeTxRecord>(Some(headers.as_ref()?))
+                .with_context(|| {
+                    format!("Failed to deserialize CSV record at line: {line} into DeTxRecord")
+                })
+                .and_then(TxRecord::try_from)
+                .inspect_err(|err| {
+                    error!("Failed to decode TxRecord from CsvRecord: {err:?}");
+                })
+                .ok()?;
+            stats.decoded += 1;
+
+            Some((line, tx))
+        });
         // Using a fused stream to avoid undefined behavior
         stream::iter(records).fuse()
     }
+
+    /// Decodes balances in the output format of the engine, i.e. with the columns: client, available, held, total and locked.
+    ///
+    /// Unlike [CsvDecoder::decode_tx], malformed rows are not skipped, but yielded as errors. Skipping a row of expected balances would silently turn into a missing client.
+    pub fn decode_balances(&mut self) -> impl FusedStream<Item = Result<Account>> {
+        let accounts = self
+            .reader
+            .deserialize::<Account>()
+            .map(|acc| acc.context("Failed to deserialize CSV record into Account"));
+
+        stream::iter(accounts).fuse()
+    }
+}
+
+impl CsvDecoder<&[u8]> {
+    /// Decodes a single line without header, e.g. `deposit, 1, 1, 1.5`. The amount column may be omitted for disputes, resolves and chargebacks.
+    ///
+    /// Unlike [CsvDecoder::decode_tx], a malformed line is returned as error, so that a producer can be told about it.
+    pub fn decode_line(line: &str) -> Result<TxRecord> {
+        let mut reader = ReaderBuilder::new()
+            .trim(Trim::All)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(line.as_bytes());
+
+        let mut record = reader
+            .records()
+            .next()
+            .context("Failed to decode an empty line")?
+            .context("Failed to read the CSV record")?;
+        // Without a header, the fields are deserialized by position, thus an omitted amount has to be added as empty field
+        if record.len() == 3 {
+            record.push_field("");
+        }
+
+        let deserialized: DeTxRecord = record
+            .deserialize(None)
+            .context("Failed to deserialize CSV record into DeTxRecord")?;
+
+        TxRecord::try_from(deserialized)
+    }
 }
 
 pub struct CsvEncoder;
 
 impl CsvEncoder {
-    pub fn encode_balances<W: Write>(sink: W, accounts: &[Account]) -> Result<()> {
+    /// The accounts are consumed incrementally, thus it is never necessary to hold all of them in memory.
+    pub async fn encode_balances<W, S>(sink: W, accounts: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Account>,
+    {
+        Self::encode(sink, accounts)
+            .await
+            .context("Failed to encode the accounts")
+    }
+
+    /// Encodes the transaction history with the columns: type, client, tx, amount and status.
+    pub async fn encode_transactions<W, S>(sink: W, transactions: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Transaction>,
+    {
+        Self::encode(sink, transactions)
+            .await
+            .context("Failed to encode the transactions")
+    }
+
+    /// Encodes the trial balance with the columns: ledger, client and balance. The client is empty for the engine's own sub-ledgers.
+    pub async fn encode_trial_balance<W: Write>(
+        sink: W,
+        trial_balance: &TrialBalance,
+    ) -> Result<()> {
+        Self::encode(sink, stream::iter(&trial_balance.lines))
+            .await
+            .context("Failed to encode the trial balance")
+    }
+
+    /// Encodes the discrepancies of a reconciliation with the columns: client, kind, field, expected, actual and delta.
+    /// The field related columns are empty for missing and unexpected clients.
+    pub async fn encode_reconciliation<W: Write>(
+        sink: W,
+        reconciliation: &Reconciliation,
+    ) -> Result<()> {
+        Self::encode(sink, stream::iter(&reconciliation.discrepancies))
+            .await
+            .context("Failed to encode the reconciliation")
+    }
+
+    async fn encode<W, S, T>(sink: W, items: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = T>,
+        T: Serialize,
+    {
         let mut writer = csv::Writer::from_writer(sink);
-        for acc in accounts {
+
+        pin!(items);
+        while let Some(item) = items.next().await {
             writer
-                .serialize(acc)
-                .context("Failed to serialize account to stdout")?;
+                .serialize(item)
+                .context("Failed to serialize item as CSV")?;
         }
         writer.flush().context("Failed to flush the writer")?;
 
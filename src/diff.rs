@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::engine::PaymentEngine;
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::transaction::TxRecord;
+use crate::reconcile::{DiscrepancyKind, Reconciliation, reconcile};
+use crate::repository::account::{AccountRepository, BalanceOrder};
+use crate::repository::transaction::TransactionRepository;
+
+/// The per-client differences between a baseline and a candidate, e.g. the balances before and after a rule change.
+///
+/// The differences are a [Reconciliation] of the candidate against the baseline. Thus, a `missing` client only exists in the baseline and an `unexpected` client only in the candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceDiff {
+    pub reconciliation: Reconciliation,
+    pub summary: DiffSummary,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    /// The number of distinct clients of both sides
+    pub clients: usize,
+    /// The number of clients, that exist on both sides, but differ in any field
+    pub changed: usize,
+    pub only_in_baseline: usize,
+    pub only_in_candidate: usize,
+    /// The number of clients, that appear more than once on either side
+    pub duplicated: usize,
+    pub baseline_total: Decimal,
+    pub candidate_total: Decimal,
+    pub baseline_locked: usize,
+    pub candidate_locked: usize,
+}
+
+impl BalanceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.reconciliation.is_reconciled()
+    }
+}
+
+/// Compares the balances of the candidate with the ones of the baseline.
+///
+/// A duplicated client is reported by the reconciliation. Like there, only its first row is compared and summed up.
+pub fn diff(baseline: Vec<Account>, candidate: Vec<Account>) -> BalanceDiff {
+    let baseline_firsts = first_per_client(&baseline);
+    let candidate_firsts = first_per_client(&candidate);
+    let sum = |accounts: &BTreeMap<ClientId, Account>| {
+        accounts
+            .values()
+            .fold(Decimal::ZERO, |sum, acc| sum.saturating_add(acc.total))
+    };
+    let locked = |accounts: &BTreeMap<ClientId, Account>| {
+        accounts.values().filter(|acc| acc.is_locked).count()
+    };
+
+    let mut summary = DiffSummary {
+        clients: baseline_firsts
+            .keys()
+            .chain(candidate_firsts.keys())
+            .collect::<BTreeSet<_>>()
+            .len(),
+        baseline_total: sum(&baseline_firsts),
+        candidate_total: sum(&candidate_firsts),
+        baseline_locked: locked(&baseline_firsts),
+        candidate_locked: locked(&candidate_firsts),
+        ..DiffSummary::default()
+    };
+
+    let reconciliation = reconcile(baseline.iter().copied(), candidate.iter().copied());
+
+    let mut changed = BTreeSet::new();
+    let mut duplicated = BTreeSet::new();
+    for discrepancy in &reconciliation.discrepancies {
+        match discrepancy.kind {
+            DiscrepancyKind::Missing => summary.only_in_baseline += 1,
+            DiscrepancyKind::Unexpected => summary.only_in_candidate += 1,
+            DiscrepancyKind::Mismatch => {
+                changed.insert(discrepancy.client);
+            }
+            DiscrepancyKind::DuplicateExpected | DiscrepancyKind::DuplicateActual => {
+                duplicated.insert(discrepancy.client);
+            }
+        }
+    }
+    summary.changed = changed.len();
+    summary.duplicated = duplicated.len();
+
+    BalanceDiff {
+        reconciliation,
+        summary,
+    }
+}
+
+fn first_per_client(accounts: &[Account]) -> BTreeMap<ClientId, Account> {
+    let mut firsts = BTreeMap::new();
+    for acc in accounts {
+        firsts.entry(acc.client_id).or_insert(*acc);
+    }
+
+    firsts
+}
+
+/// Runs the same records through both engines and compares the resulting balances.
+///
+/// The engines are expected to start with empty repositories and to differ only in their configuration.
+/// The records are processed by the baseline first and by the candidate afterwards, thus they have to be replayable.
+/// An engine in [crate::prelude::FailureMode::Strict] aborts at the first failing record, which is returned as error.
+pub async fn diff_engines<AR1, TR1, AR2, TR2>(
+    baseline: &PaymentEngine<AR1, TR1>,
+    candidate: &PaymentEngine<AR2, TR2>,
+    records: &[TxRecord],
+) -> Result<BalanceDiff>
+where
+    AR1: AccountRepository,
+    TR1: TransactionRepository,
+    AR2: AccountRepository,
+    TR2: TransactionRepository,
+{
+    baseline
+        .process(stream::iter(records.iter().copied()).fuse())
+        .await
+        .context("The baseline engine failed")?;
+    candidate
+        .process(stream::iter(records.iter().copied()).fuse())
+        .await
+        .context("The candidate engine failed")?;
+
+    let baseline = baseline
+        .accounts()
+        .balances(BalanceOrder::ClientId)
+        .collect()
+        .await;
+    let candidate = candidate
+        .accounts()
+        .balances(BalanceOrder::ClientId)
+        .collect()
+        .await;
+
+    Ok(diff(baseline, candidate))
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::dec;
+
+    use super::*;
+
+    fn account(client: u16, total: Decimal, is_locked: bool) -> Account {
+        Account {
+            available: total,
+            total,
+            is_locked,
+            ..Account::new(ClientId::new(client))
+        }
+    }
+
+    #[test]
+    fn summarizes_the_differences() {
+        let baseline = vec![
+            account(1, dec!(10), false),
+            account(2, dec!(5), false),
+            account(3, dec!(1), false),
+        ];
+        let candidate = vec![
+            account(1, dec!(10), false),
+            account(2, dec!(0), true),
+            account(4, dec!(2), false),
+        ];
+
+        let diff = diff(baseline, candidate);
+
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                clients: 4,
+                changed: 1,
+                only_in_baseline: 1,
+                only_in_candidate: 1,
+                duplicated: 0,
+                baseline_total: dec!(16),
+                candidate_total: dec!(12),
+                baseline_locked: 0,
+                candidate_locked: 1,
+            }
+        );
+        // available, total and locked of client 2, as well as client 3 and 4
+        assert_eq!(diff.reconciliation.discrepancies.len(), 5);
+    }
+
+    #[test]
+    fn duplicated_clients_are_counted_once() {
+        let baseline = vec![account(1, dec!(10), false), account(1, dec!(10), false)];
+        let candidate = vec![
+            account(1, dec!(10), false),
+            account(2, dec!(3), true),
+            account(2, dec!(4), false),
+        ];
+
+        let diff = diff(baseline, candidate);
+
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                clients: 2,
+                changed: 0,
+                only_in_baseline: 0,
+                only_in_candidate: 1,
+                duplicated: 2,
+                baseline_total: dec!(10),
+                candidate_total: dec!(13),
+                baseline_locked: 0,
+                candidate_locked: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn equal_balances_have_no_differences() {
+        let balances = vec![account(1, dec!(10), true)];
+
+        let diff = diff(balances.clone(), balances);
+
+        assert!(diff.is_empty(), "unexpected differences");
+        assert_eq!(diff.summary.changed, 0);
+    }
+}
@@ -1,23 +1,168 @@
+use std::num::NonZeroUsize;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{Context, Result, anyhow, bail};
 use futures::stream::{FusedStream, StreamExt};
+use serde::Serialize;
 use tokio::pin;
-use tracing::error;
+use tokio::sync::broadcast;
+use tracing::{Instrument, Span, debug_span, error, info_span, instrument, warn};
 
-use crate::models::account::Direction;
+use crate::audit::AuditSink;
+use crate::config::{
+    DisputePolicy, EngineConfig, FailureMode, IdempotencyPolicy, LockedAccountPolicy,
+};
+use crate::csv::DecodeStats;
+use crate::events::AccountEvent;
+use crate::limits::LimitTracker;
+use crate::metrics::EngineMetrics;
+use crate::models::NonNegativeDecimal;
+use crate::models::account::{Account, Direction};
+use crate::models::client::ClientId;
+use crate::models::journal::postings_for;
+use crate::models::ledger::Outcome;
+use crate::models::rejection::Rejection;
 use crate::models::transaction::{
-    Chargeback, Deposit, Dispute, Resolve, Transaction, TransactionStatus, TransactionType,
-    TxRecord, Withdrawal,
+    Chargeback, Deposit, Dispute, Resolve, Transaction, TransactionId, TransactionStatus,
+    TransactionType, TxRecord, Withdrawal,
 };
+use crate::policy::{Decision, PolicyContext, TxPolicy};
 use crate::repository::account::AccountRepository;
-use crate::repository::transaction::TransactionRepository;
+use crate::repository::journal::JournalRepository;
+use crate::repository::ledger::LedgerRepository;
+use crate::repository::seen_index::SeenTxIndex;
+use crate::repository::transaction::{TransactionRepository, TxQuery};
+use crate::summary::{RunStats, RunSummary};
 
 /// We use static dispatch for the engine, as dyn dispatch is not necessary for this use case.
 /// Dyn dispatch would introduce some complexity in the correspondig respository types, as well.
 pub struct PaymentEngine<AR, TR> {
     accounts: Arc<AR>,
     transactions: Arc<TR>,
+    config: EngineConfig,
+    limits: LimitTracker,
+    // Optional extensions are dyn dispatched. This keeps the type signature of the engine stable, regardless of the configured extensions.
+    seen_index: Option<Arc<dyn SeenTxIndex>>,
+    ledger: Option<Arc<dyn LedgerRepository>>,
+    journal: Option<Arc<dyn JournalRepository>>,
+    audit_log: Option<Arc<dyn AuditSink>>,
+    metrics: Option<Arc<EngineMetrics>>,
+    run_stats: Option<Arc<RunStats>>,
+    policies: Vec<Arc<dyn TxPolicy>>,
+    // Sending only fails, if there is no subscriber. Thus the events are only collected, while someone listens.
+    events: broadcast::Sender<AccountEvent>,
+}
+
+/// The builder is the place to configure the business rules, see [EngineConfig].
+/// Both repositories are mandatory, thus they are already required for constructing the builder.
+pub struct PaymentEngineBuilder<AR, TR> {
+    accounts: Arc<AR>,
+    transactions: Arc<TR>,
+    config: EngineConfig,
+    seen_index: Option<Arc<dyn SeenTxIndex>>,
+    ledger: Option<Arc<dyn LedgerRepository>>,
+    journal: Option<Arc<dyn JournalRepository>>,
+    audit_log: Option<Arc<dyn AuditSink>>,
+    metrics: Option<Arc<EngineMetrics>>,
+    run_stats: Option<Arc<RunStats>>,
+    policies: Vec<Arc<dyn TxPolicy>>,
+    event_capacity: NonZeroUsize,
+}
+
+impl<AR, TR> PaymentEngineBuilder<AR, TR>
+where
+    AR: AccountRepository,
+    TR: TransactionRepository,
+{
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The index replaces the lookup of the full transaction in the replay check, see [SeenTxIndex].
+    /// It must be empty or reflect the content of the transaction repository.
+    pub fn seen_index(mut self, seen_index: Arc<dyn SeenTxIndex>) -> Self {
+        self.seen_index = Some(seen_index);
+        self
+    }
+
+    /// Every processed record is appended to the ledger, together with its outcome and the account before and after it.
+    pub fn ledger(mut self, ledger: Arc<dyn LedgerRepository>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Every applied record is posted to the double-entry journal, see [crate::models::journal::postings_for].
+    /// The journal runs in parallel to the account repository, which stays the source of truth, see [JournalRepository].
+    pub fn journal(mut self, journal: Arc<dyn JournalRepository>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Every applied record is appended to the audit log, together with the resulting account of the client.
+    /// The caller is responsible for flushing it after the processing.
+    pub fn audit_log(mut self, audit_log: Arc<dyn AuditSink>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Every record and every repository call of the engine is measured, see [EngineMetrics].
+    pub fn metrics(mut self, metrics: Arc<EngineMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The outcome of every record is counted for the summary of a batch run, see [PaymentEngine::run_summary].
+    pub fn run_stats(mut self, run_stats: Arc<RunStats>) -> Self {
+        self.run_stats = Some(run_stats);
+        self
+    }
+
+    /// Adds a policy, that is evaluated for every record before it is applied, see [TxPolicy]. The policies are evaluated in the order they have been added.
+    pub fn policy(mut self, policy: Arc<dyn TxPolicy>) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// The number of events buffered for a subscriber, see [PaymentEngine::subscribe]. A subscriber, that falls further behind, misses the oldest events.
+    pub fn event_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.event_capacity = capacity;
+        self
+    }
+
+    pub fn build(self) -> PaymentEngine<AR, TR> {
+        let (events, _) = broadcast::channel(self.event_capacity.get());
+
+        PaymentEngine {
+            accounts: self.accounts,
+            transactions: self.transactions,
+            limits: LimitTracker::new(self.config.limits.clone()),
+            config: self.config,
+            seen_index: self.seen_index,
+            ledger: self.ledger,
+            journal: self.journal,
+            audit_log: self.audit_log,
+            metrics: self.metrics,
+            run_stats: self.run_stats,
+            policies: self.policies,
+            events,
+        }
+    }
+}
+
+/// The outcome of a record, as answered to its producer, see [PaymentEngine::submit].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum RecordOutcome {
+    /// Carries the resulting account of the client
+    Applied { account: Option<Account> },
+    Rejected {
+        rejection: Rejection,
+        message: String,
+    },
+    /// An unexpected error, e.g. of a repository. The record might be retried.
+    Failed { message: String },
 }
 
 impl<AR, TR> PaymentEngine<AR, TR>
@@ -25,190 +170,738 @@ where
     AR: AccountRepository,
     TR: TransactionRepository,
 {
+    pub const DEFAULT_EVENT_CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+
+    /// Constructs the engine with the default [EngineConfig]. Use [PaymentEngine::builder] for a custom configuration.
     pub fn new(accounts: Arc<AR>, transactions: Arc<TR>) -> Self {
-        Self {
+        Self::builder(accounts, transactions).build()
+    }
+
+    pub fn builder(accounts: Arc<AR>, transactions: Arc<TR>) -> PaymentEngineBuilder<AR, TR> {
+        PaymentEngineBuilder {
             accounts,
             transactions,
+            config: EngineConfig::default(),
+            seen_index: None,
+            ledger: None,
+            journal: None,
+            audit_log: None,
+            metrics: None,
+            run_stats: None,
+            policies: Vec::new(),
+            event_capacity: Self::DEFAULT_EVENT_CAPACITY,
         }
     }
 
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// The repository of the accounts, e.g. for reading the balances after the processing.
+    pub fn accounts(&self) -> &Arc<AR> {
+        &self.accounts
+    }
+
+    pub fn transactions(&self) -> &Arc<TR> {
+        &self.transactions
+    }
+
+    /// Subscribes to the [AccountEvent]s of every record applied from now on, in the order of the records.
+    pub fn subscribe(&self) -> broadcast::Receiver<AccountEvent> {
+        self.events.subscribe()
+    }
+
     /// This is the main entry point for processing the entities on the stream
-    pub async fn process<S>(&self, stream: S)
+    ///
+    /// With [FailureMode::Lenient] a failing record is logged and the processing continues, thus it always returns `Ok`.
+    /// With [FailureMode::Strict] the processing stops at the first failing record, returning its error.
+    pub async fn process<S>(&self, stream: S) -> Result<()>
     where
         S: FusedStream<Item = TxRecord>,
+    {
+        self.process_lines(stream.map(|tx| (None, tx))).await
+    }
+
+    /// Like [PaymentEngine::process], but every record is paired with its line number in the input, see [crate::prelude::CsvDecoder::decode_numbered_tx].
+    /// The line number is recorded in the span of the record.
+    pub async fn process_numbered<S>(&self, stream: S) -> Result<()>
+    where
+        S: FusedStream<Item = (u64, TxRecord)>,
+    {
+        self.process_lines(stream.map(|(line, tx)| (Some(line), tx)))
+            .await
+    }
+
+    async fn process_lines<S>(&self, stream: S) -> Result<()>
+    where
+        S: FusedStream<Item = (Option<u64>, TxRecord)>,
     {
         pin!(stream);
 
-        while let Some(tx) = stream.next().await {
-            // The final result marks the root element, which makes it an ideal candidate for being reported in a telemetry system.
-            // Because this experiment does not have proper telemtry, I decided to drop it (see the inspect_err below)
-            let _res: Result<()> = match tx {
-                // Main dispatcher & extension point:
-                // 1. if new variants might come up
-                // 2. but also to change the runtime behavior, e.g by spawning the work as dedicated tasks
-                TxRecord::Deposit(deposit) => {
-                    let existing_tx = self.transactions.get(deposit.tx_id).await;
-
-                    match prevent_replay_attack(existing_tx.as_ref()) {
-                        Ok(()) => self.handle_deposit(deposit).await,
-                        Err(err) => Err(err),
-                    }
+        self.start_run().await?;
+
+        while let Some((line, tx)) = stream.next().await {
+            // The final result marks the root element, thus it is logged within the span of the record
+            let res = async {
+                self.measure_record(tx).await.inspect_err(|err| {
+                    error!("Failed to process TxRecord: {err:?}");
+                })
+            }
+            .instrument(record_span(&tx, line))
+            .await;
+
+            if self.config.failure_mode == FailureMode::Strict
+                && let Err(err) = res
+            {
+                self.abort_run(line, &err);
+                return Err(
+                    err.context("Aborted processing, because a TxRecord failed in strict mode")
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts the run of the [RunStats] with the current number of accounts, if configured
+    pub(crate) async fn start_run(&self) -> Result<()> {
+        if let Some(run_stats) = &self.run_stats {
+            let totals = self
+                .call_repository("accounts", "totals", self.accounts.totals())
+                .await?;
+            run_stats.start(totals.accounts);
+        }
+
+        Ok(())
+    }
+
+    /// Records the error, that aborted the run, in the [RunStats], if configured
+    pub(crate) fn abort_run(&self, line: Option<u64>, err: &anyhow::Error) {
+        if let Some(run_stats) = &self.run_stats {
+            run_stats.abort(line, err);
+        }
+    }
+
+    /// Processes a single record and returns its outcome. Unlike [PaymentEngine::process], a failure is always returned.
+    ///
+    /// With metrics, the outcome and the latency of the record are recorded, see [EngineMetrics].
+    pub async fn process_record(&self, tx: TxRecord) -> Result<()> {
+        self.measure_record(tx)
+            .instrument(record_span(&tx, None))
+            .await
+    }
+
+    async fn measure_record(&self, tx: TxRecord) -> Result<()> {
+        let start = Instant::now();
+        let res = self.apply_record(tx).await;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_record(&tx, &res, start.elapsed());
+        }
+        if let Some(run_stats) = &self.run_stats {
+            run_stats.observe_record(&tx, &res);
+        }
+
+        res
+    }
+
+    /// Applies a single record to the repositories and the optional extensions.
+    ///
+    /// With a ledger, the account of the client is captured before and after the record and appended together with the outcome.
+    /// With a journal, the postings of an applied record are posted. With an audit log, an applied record is appended to it.
+    /// Finally, the [AccountEvent]s of an applied record are published to the subscribers.
+    ///
+    /// A record rejected by a [TxPolicy] is never dispatched, but reported to the extensions like any other rejection.
+    async fn apply_record(&self, tx: TxRecord) -> Result<()> {
+        let client_id = tx.client_id();
+        let has_subscribers = self.events.receiver_count() > 0;
+        // A chargeback only publishes the lock, if the account hasn't been locked before
+        let needs_before =
+            self.ledger.is_some() || (has_subscribers && matches!(tx, TxRecord::Chargeback(_)));
+        let before = match needs_before {
+            true => self.get_account(client_id).await?,
+            false => None,
+        };
+
+        let res = match self.evaluate_policies(tx).await {
+            Ok(()) => self.dispatch(tx).await,
+            Err(err) => Err(err),
+        };
+
+        // The record has been applied at this point, thus a failing sink doesn't change its outcome
+        if let Ok(referenced) = &res
+            && let Some(journal) = &self.journal
+            && let Err(err) = post_to_journal(journal.as_ref(), tx, referenced.as_ref()).await
+        {
+            self.sink_failed("journal", err);
+        }
+        let res = res.map(|_referenced| ());
+
+        let audit_log = self.audit_log.as_ref().filter(|_| res.is_ok());
+        let publish = has_subscribers && res.is_ok();
+        if self.ledger.is_none() && audit_log.is_none() && !publish {
+            return res;
+        }
+
+        let after = match self.get_account(client_id).await {
+            Ok(after) => after,
+            Err(err) => {
+                // Without the resulting account, none of the sinks can be written
+                let sinks = [
+                    audit_log.map(|_| "audit_log"),
+                    self.ledger.as_ref().map(|_| "ledger"),
+                ];
+                for sink in sinks.into_iter().flatten() {
+                    self.sink_failed(sink, anyhow!("{err:#}"));
                 }
 
-                TxRecord::Withdrawal(withdrawal) => {
-                    let existing_tx = self.transactions.get(withdrawal.tx_id).await;
+                return res;
+            }
+        };
+
+        if let Some(audit_log) = audit_log
+            && let Err(err) = audit_log
+                .append(tx, after)
+                .context("Failed to append the TxRecord to the audit log")
+        {
+            self.sink_failed("audit_log", err);
+        }
+
+        if let Some(ledger) = &self.ledger
+            && let Err(err) = ledger
+                .append(tx, Outcome::from(&res), before, after)
+                .await
+                .context("Failed to append the TxRecord to the ledger")
+        {
+            self.sink_failed("ledger", err);
+        }
 
-                    match prevent_replay_attack(existing_tx.as_ref()) {
-                        Ok(()) => self.handle_withdrawal(withdrawal).await,
-                        Err(err) => Err(err),
+        if publish && let Some(after) = after {
+            for event in AccountEvent::for_record(&tx, before.as_ref(), after) {
+                // The last subscriber might have gone in the meantime
+                let _ = self.events.send(event);
+            }
+        }
+
+        res
+    }
+
+    /// A sink, i.e. the journal, the audit log or the ledger, that fails after the record has been processed, is reported separately, because the record can't be undone anymore.
+    /// The failure is logged and counted by the [EngineMetrics] and the [RunStats], but it doesn't change the outcome of the record.
+    fn sink_failed(&self, sink: &'static str, err: anyhow::Error) {
+        error!("Failed to write the TxRecord to the {sink}: {err:?}");
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_sink_failure(sink);
+        }
+        if let Some(run_stats) = &self.run_stats {
+            run_stats.observe_sink_failure(sink);
+        }
+    }
+
+    /// Processes a single record like [PaymentEngine::process_record] and classifies the result for answering the producer of the record.
+    pub async fn submit(&self, tx: TxRecord) -> RecordOutcome {
+        async {
+            let err = match self.measure_record(tx).await {
+                Ok(()) => match self.get_account(tx.client_id()).await {
+                    Ok(account) => return RecordOutcome::Applied { account },
+                    Err(err) => err,
+                },
+                Err(err) => err,
+            };
+
+            match Rejection::of(&err) {
+                Some(rejection) => RecordOutcome::Rejected {
+                    rejection: rejection.clone(),
+                    message: rejection.to_string(),
+                },
+                None => {
+                    error!("Failed to process TxRecord: {err:?}");
+                    RecordOutcome::Failed {
+                        message: format!("{err:#}"),
                     }
                 }
+            }
+        }
+        .instrument(record_span(&tx, None))
+        .await
+    }
+
+    /// Refreshes the gauges from the repositories and encodes all metrics in the Prometheus text format. Returns `None` without metrics.
+    pub async fn encode_metrics(&self) -> Result<Option<String>> {
+        let Some(metrics) = &self.metrics else {
+            return Ok(None);
+        };
+
+        let totals = self.accounts.totals().await?;
+        let open_disputes = self
+            .transactions
+            .count(&TxQuery::new().status(TransactionStatus::Disputed))
+            .await?;
+        metrics.accounts.set(totals.accounts as i64);
+        metrics.locked_accounts.set(totals.locked as i64);
+        metrics.open_disputes.set(open_disputes as i64);
+
+        metrics.encode().map(Some)
+    }
+
+    /// Evaluates the policies in order, until the first one rejects the record. Flags are logged and counted in the metrics.
+    async fn evaluate_policies(&self, tx: TxRecord) -> Result<()> {
+        if self.policies.is_empty() {
+            return Ok(());
+        }
+
+        let context = PolicyContext {
+            account: self.get_account(tx.client_id()).await?,
+            transactions: self.transactions.as_ref(),
+        };
 
-                TxRecord::Dispute(dispute) => {
-                    let referenced_tx = self.transactions.get(dispute.tx_id).await;
-
-                    match tx_exists_and_has_been_processed(referenced_tx.as_ref()) {
-                        Ok(tx) => {
-                            // Remark: the into_inner is a shortcut because lack of time.
-                            // It would be better to extend the NonNegativeDecimal, allowing the necessary operations
-                            let amount = tx.amount.into_inner();
-                            // Direction is a workaround. Please see the comment on the type definition
-                            let direction = if tx.tx_type == TransactionType::Withdrawal {
-                                Direction::Increase(amount)
-                            } else {
-                                Direction::Decrease(amount)
-                            };
-
-                            self.handle_dispute(dispute, direction).await
-                        }
-
-                        Err(err) => Err(err),
+        for policy in &self.policies {
+            let name = policy.name();
+            let decision = policy
+                .evaluate(&tx, &context)
+                .instrument(debug_span!("policy", name))
+                .await
+                .with_context(|| format!("Failed to evaluate the policy: {name}"))?;
+
+            match decision {
+                Decision::Allow => {}
+                Decision::Reject(cause) => bail!(Rejection::PolicyRejected {
+                    policy: name,
+                    cause
+                }),
+                Decision::Flag(cause) => {
+                    warn!("Flagged TxRecord by the policy: {name} for: {cause}");
+                    if let Some(metrics) = &self.metrics {
+                        metrics.observe_flag(name, &cause);
                     }
                 }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Summarizes the run, given the counts of the decoder, e.g. [crate::prelude::CsvDecoder::stats]. Returns `None` without [RunStats].
+    ///
+    /// The locked accounts and the open disputes are read from the repositories, thus the summary should be taken right after the processing.
+    pub async fn run_summary(&self, decoding: DecodeStats) -> Result<Option<RunSummary>> {
+        let Some(run_stats) = &self.run_stats else {
+            return Ok(None);
+        };
+
+        let totals = self.accounts.totals().await?;
+        let open_disputes = self
+            .transactions
+            .count(&TxQuery::new().status(TransactionStatus::Disputed))
+            .await?;
+
+        Ok(Some(run_stats.summarize(
+            decoding,
+            totals.accounts,
+            totals.locked,
+            open_disputes,
+        )))
+    }
+
+    /// Runs a repository call within its span and measures its latency, if there are metrics.
+    async fn call_repository<F>(
+        &self,
+        repository: &'static str,
+        operation: &'static str,
+        call: F,
+    ) -> F::Output
+    where
+        F: Future,
+    {
+        let start = Instant::now();
+        let output = call
+            .instrument(debug_span!("repository", repository, operation))
+            .await;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_repository(repository, operation, start.elapsed());
+        }
+
+        output
+    }
+
+    async fn get_account(&self, client_id: ClientId) -> Result<Option<Account>> {
+        self.call_repository("accounts", "get", self.accounts.get(client_id))
+            .await
+            .with_context(|| {
+                format!("Failed to get account from AccountRepo for client_id: {client_id:?}")
+            })
+    }
+
+    /// Returns the transaction referenced by a dispute, resolve or chargeback, as it was before the record has been applied.
+    async fn dispatch(&self, tx: TxRecord) -> Result<Option<Transaction>> {
+        match tx {
+            // Main dispatcher & extension point:
+            // 1. if new variants might come up
+            // 2. but also to change the runtime behavior, e.g by spawning the work as dedicated tasks
+            TxRecord::Deposit(deposit) => {
+                let (existing_tx, expired) = self.find_existing(deposit.tx_id).await;
+
+                match prevent_replay_attack(
+                    existing_tx.as_ref(),
+                    deposit.client_id,
+                    self.config.idempotency,
+                )
+                .and_then(|()| prevent_expired_replay(deposit.tx_id, expired))
+                .and_then(|()| self.validate_amount(deposit.amount))
+                .and_then(|()| {
+                    self.limits.check(
+                        deposit.client_id,
+                        TransactionType::Deposit,
+                        deposit.amount.into_inner(),
+                    )
+                }) {
+                    // Passing the replay check with an existing tx means, that a failed tx is retried
+                    Ok(()) => self
+                        .handle_deposit(deposit, existing_tx.is_some())
+                        .await
+                        .inspect(|()| {
+                            self.limits.record(
+                                deposit.client_id,
+                                TransactionType::Deposit,
+                                deposit.amount.into_inner(),
+                            )
+                        })
+                        .map(|()| None),
+                    Err(err) => Err(err),
+                }
+            }
 
-                TxRecord::Resolve(resolve) => {
-                    let referenced_tx = self.transactions.get(resolve.tx_id).await;
+            TxRecord::Withdrawal(withdrawal) => {
+                let (existing_tx, expired) = self.find_existing(withdrawal.tx_id).await;
 
-                    match tx_exists_and_has_been_disputed(referenced_tx.as_ref()) {
-                        Ok(tx) => {
-                            let amount = tx.amount.into_inner();
+                match prevent_replay_attack(
+                    existing_tx.as_ref(),
+                    withdrawal.client_id,
+                    self.config.idempotency,
+                )
+                .and_then(|()| prevent_expired_replay(withdrawal.tx_id, expired))
+                .and_then(|()| self.validate_amount(withdrawal.amount))
+                .and_then(|()| {
+                    self.limits.check(
+                        withdrawal.client_id,
+                        TransactionType::Withdrawal,
+                        withdrawal.amount.into_inner(),
+                    )
+                }) {
+                    // Passing the replay check with an existing tx means, that a failed tx is retried
+                    Ok(()) => self
+                        .handle_withdrawal(withdrawal, existing_tx.is_some())
+                        .await
+                        .inspect(|()| {
+                            self.limits.record(
+                                withdrawal.client_id,
+                                TransactionType::Withdrawal,
+                                withdrawal.amount.into_inner(),
+                            )
+                        })
+                        .map(|()| None),
+                    Err(err) => Err(err),
+                }
+            }
 
-                            let direction = if tx.tx_type == TransactionType::Withdrawal {
-                                Direction::Increase(amount)
-                            } else {
-                                Direction::Decrease(amount)
-                            };
+            TxRecord::Dispute(dispute) => {
+                let referenced_tx = self
+                    .call_repository("transactions", "get", self.transactions.get(dispute.tx_id))
+                    .await;
+                let expired = self
+                    .check_expiry(dispute.tx_id, referenced_tx.as_ref())
+                    .await;
 
-                            self.handle_resolve(resolve, direction).await
-                        }
+                match ensure_within_dispute_window(dispute.tx_id, expired)
+                    .and_then(|()| {
+                        tx_exists_and_has_been_processed(dispute.tx_id, referenced_tx.as_ref())
+                    })
+                    .and_then(|tx| self.ensure_disputable(tx))
+                {
+                    Ok(tx) => {
+                        // Remark: the into_inner is a shortcut because lack of time.
+                        // It would be better to extend the NonNegativeDecimal, allowing the necessary operations
+                        let amount = tx.amount.into_inner();
+                        // Direction is a workaround. Please see the comment on the type definition
+                        let direction = if tx.tx_type == TransactionType::Withdrawal {
+                            Direction::Increase(amount)
+                        } else {
+                            Direction::Decrease(amount)
+                        };
 
-                        Err(err) => Err(err),
+                        self.handle_dispute(dispute, direction)
+                            .await
+                            .map(|()| Some(*tx))
                     }
+
+                    Err(err) => Err(err),
                 }
+            }
 
-                TxRecord::Chargeback(cb) => {
-                    let referenced_tx = self.transactions.get(cb.tx_id).await;
+            TxRecord::Resolve(resolve) => {
+                let referenced_tx = self
+                    .call_repository("transactions", "get", self.transactions.get(resolve.tx_id))
+                    .await;
+                let expired = self
+                    .check_expiry(resolve.tx_id, referenced_tx.as_ref())
+                    .await;
 
-                    match tx_exists_and_has_been_resolved(referenced_tx.as_ref()) {
-                        Ok(tx) => {
-                            let amount = tx.amount.into_inner();
+                match ensure_within_dispute_window(resolve.tx_id, expired).and_then(|()| {
+                    tx_exists_and_has_been_disputed(resolve.tx_id, referenced_tx.as_ref())
+                }) {
+                    Ok(tx) => {
+                        let amount = tx.amount.into_inner();
 
-                            let direction = if tx.tx_type == TransactionType::Withdrawal {
-                                Direction::Increase(amount)
-                            } else {
-                                Direction::Decrease(amount)
-                            };
+                        let direction = if tx.tx_type == TransactionType::Withdrawal {
+                            Direction::Increase(amount)
+                        } else {
+                            Direction::Decrease(amount)
+                        };
+
+                        self.handle_resolve(resolve, direction)
+                            .await
+                            .map(|()| Some(*tx))
+                    }
+
+                    Err(err) => Err(err),
+                }
+            }
 
-                            self.handle_chargeback(cb, direction).await
-                        }
+            TxRecord::Chargeback(cb) => {
+                let referenced_tx = self
+                    .call_repository("transactions", "get", self.transactions.get(cb.tx_id))
+                    .await;
+                let expired = self.check_expiry(cb.tx_id, referenced_tx.as_ref()).await;
 
-                        Err(err) => Err(err),
+                match ensure_within_dispute_window(cb.tx_id, expired).and_then(|()| {
+                    tx_exists_and_has_been_resolved(cb.tx_id, referenced_tx.as_ref())
+                }) {
+                    Ok(tx) => {
+                        let amount = tx.amount.into_inner();
+
+                        let direction = if tx.tx_type == TransactionType::Withdrawal {
+                            Direction::Increase(amount)
+                        } else {
+                            Direction::Decrease(amount)
+                        };
+
+                        self.handle_chargeback(cb, direction)
+                            .await
+                            .map(|()| Some(*tx))
                     }
+
+                    Err(err) => Err(err),
                 }
             }
-            .inspect_err(|err| {
-                error!("Failed to process TxRecord: {err:?}");
+        }
+    }
+
+    /// Looks up a potentially existing transaction for the replay check, and whether it already expired.
+    /// With a [SeenTxIndex], the repository is only queried for ids that have been seen before.
+    async fn find_existing(&self, tx_id: TransactionId) -> (Option<Transaction>, bool) {
+        if let Some(seen_index) = &self.seen_index
+            && !seen_index.contains(tx_id)
+        {
+            return (None, false);
+        }
+
+        let existing_tx = self
+            .call_repository("transactions", "get", self.transactions.get(tx_id))
+            .await;
+        let expired = self.check_expiry(tx_id, existing_tx.as_ref()).await;
+
+        (existing_tx, expired)
+    }
+
+    /// Only transactions, that could not be found, are checked. This saves the additional lookup for the common case.
+    async fn check_expiry(&self, tx_id: TransactionId, found: Option<&Transaction>) -> bool {
+        found.is_none()
+            && self
+                .call_repository(
+                    "transactions",
+                    "is_expired",
+                    self.transactions.is_expired(tx_id),
+                )
+                .await
+    }
+
+    /// A retried transaction replaces the stored failed one, otherwise the repository guards against overwriting an existing transaction
+    async fn persist_tx(&self, tx: Transaction, is_retry: bool) -> Result<()> {
+        if is_retry {
+            self.call_repository("transactions", "upsert", self.transactions.upsert(tx))
+                .await
+        } else {
+            self.call_repository("transactions", "insert", self.transactions.insert(tx))
+                .await?;
+
+            if let Some(seen_index) = &self.seen_index {
+                seen_index.insert(tx.id);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn validate_amount(&self, amount: NonNegativeDecimal) -> Result<()> {
+        let amount = amount.into_inner();
+
+        if let Some(precision) = self.config.precision
+            && amount.normalize().scale() > precision
+        {
+            bail!(Rejection::PrecisionExceeded { amount, precision });
+        }
+
+        if let Some(max_amount) = self.config.max_amount
+            && amount > max_amount
+        {
+            bail!(Rejection::MaxAmountExceeded { amount, max_amount });
+        }
+
+        Ok(())
+    }
+
+    fn ensure_disputable<'a>(&self, tx: &'a Transaction) -> Result<&'a Transaction> {
+        if self.config.dispute == DisputePolicy::DepositsOnly
+            && tx.tx_type == TransactionType::Withdrawal
+        {
+            bail!(Rejection::NotDisputable { tx: tx.id });
+        }
+
+        Ok(tx)
+    }
+
+    /// Enforces the [LockedAccountPolicy]. Only withdrawals are affected by [LockedAccountPolicy::BlockWithdrawals].
+    fn ensure_not_locked(&self, acc: &Account, is_withdrawal: bool) -> Result<()> {
+        let rejected = match self.config.locked_account {
+            LockedAccountPolicy::Ignore => false,
+            LockedAccountPolicy::BlockWithdrawals => is_withdrawal,
+            LockedAccountPolicy::Freeze => true,
+        };
+
+        if acc.is_locked && rejected {
+            bail!(Rejection::AccountLocked {
+                client: acc.client_id
             });
         }
+
+        Ok(())
     }
 
-    async fn handle_deposit(&self, deposit: Deposit) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_deposit(&self, deposit: Deposit, is_retry: bool) -> Result<()> {
         let client_id = deposit.client_id;
         let tx_id = deposit.tx_id;
         // This future serves a very important responsibility:
         // It defines a scope of execution and here also to have some kind of "transactional" context.
         // I find this pattern usefull, because I can post process the result, regardless if we left it early (due to an error and the ? operator) or if the futures succeeded
-        let tx = async move {
-            let mut new_acc = self.accounts.get_or_new(client_id).await.with_context(|| {
+        let res = async move {
+            let mut new_acc = self.call_repository("accounts", "get_or_new", self.accounts.get_or_new(client_id)).await.with_context(|| {
                 format!("Failed to get account from AccountRepo for client_id: {client_id:?}")
             })?;
 
+            self.ensure_not_locked(&new_acc, false)?;
+
             new_acc.deposit(deposit.amount.into_inner()).with_context(|| format!("Failed to perform the deposit for a client_id: {client_id:?} and tx_id: {tx_id:?}"))?;
 
-            self.accounts.upsert(new_acc).await.with_context(|| {
+            self.call_repository("accounts", "upsert", self.accounts.upsert(new_acc)).await.with_context(|| {
                 format!("Failed to upsert account in AccountRepo: {new_acc:?}")
             })
         }
-        .await
-        .map(|()| Transaction::from_deposit(deposit, TransactionStatus::Processed))
-        .unwrap_or_else(|_err| Transaction::from_deposit(deposit, TransactionStatus::Failed));
+        .await;
+
+        // The transaction is persisted in both cases, but a failure is still reported to the caller
+        let status = if res.is_ok() {
+            TransactionStatus::Processed
+        } else {
+            TransactionStatus::Failed
+        };
 
-        self.transactions.insert(tx).await.with_context(|| {
+        self.persist_tx(Transaction::from_deposit(deposit, status), is_retry).await.with_context(|| {
                 format!(
                     "Failed to upsert transaction in TransactionRepo for client_id: {client_id:?} and tx_id: {tx_id:?}",
                 )
-            })
+            })?;
+
+        res
     }
 
-    async fn handle_withdrawal(&self, withdrawal: Withdrawal) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_withdrawal(&self, withdrawal: Withdrawal, is_retry: bool) -> Result<()> {
         let client_id = withdrawal.client_id;
         let tx_id = withdrawal.tx_id;
 
-        let tx = async move {
-            let acc = self.accounts.get(client_id).await.with_context(|| {
+        let res = async move {
+            let acc = self.call_repository("accounts", "get", self.accounts.get(client_id)).await.with_context(|| {
                 format!("Failed to get account from AccountRepo for client_id: {client_id:?}")
             })?;
 
-            let mut acc = acc.with_context(|| format!("Failed to withdraw from an non-existing account. Client {client_id:?} and tx_id: {tx_id:?}"))?;
+            let acc = match acc {
+                None if self.config.withdrawal_opens_account => {
+                    // The account is opened regardless of the outcome of the withdrawal itself
+                    let new_acc = Account::new(client_id);
+                    self.call_repository("accounts", "upsert", self.accounts.upsert(new_acc)).await.with_context(|| {
+                        format!("Failed to upsert account in AccountRepo: {new_acc:?}")
+                    })?;
+
+                    Some(new_acc)
+                }
+                acc => acc,
+            };
+
+            let Some(mut acc) = acc else {
+                bail!(Rejection::UnknownAccount { client: client_id });
+            };
+
+            self.ensure_not_locked(&acc, true)?;
 
             acc.try_withdrawal(withdrawal.amount.into_inner()).with_context(|| format!("Failed to perform the withdrawal for a client)_id: {client_id:?} and tx_id: {tx_id:?}"))?;
 
-            self.accounts.upsert(acc).await.with_context(|| {
+            self.call_repository("accounts", "upsert", self.accounts.upsert(acc)).await.with_context(|| {
                 format!("Failed to upsert account in AccountRepo: {acc:?}")
             })
         }
-        .await
-        .map(|()| Transaction::from_withdrawal(withdrawal, TransactionStatus::Processed))
-        .unwrap_or_else(|_err| Transaction::from_withdrawal(withdrawal, TransactionStatus::Failed));
+        .await;
 
-        self.transactions.insert(tx).await.with_context(|| {
+        // The transaction is persisted in both cases, but a failure is still reported to the caller
+        let status = if res.is_ok() {
+            TransactionStatus::Processed
+        } else {
+            TransactionStatus::Failed
+        };
+
+        self.persist_tx(Transaction::from_withdrawal(withdrawal, status), is_retry).await.with_context(|| {
                 format!(
                     "Failed to upsert transaction in TransactionRepo for client_id: {client_id:?} and tx_id: {tx_id:?}",
                 )
-            })
+            })?;
+
+        res
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn handle_dispute(&self, dispute: Dispute, direction: Direction) -> Result<()> {
         let client_id = dispute.client_id;
         let tx_id = dispute.tx_id;
 
         let res = async move {
-            let acc = self.accounts.get(client_id).await;
-
-            let Ok(Some(mut acc)) = acc else {
-                return Err(anyhow!("Failed to get account from AccountRepo for client_id: {client_id:?}"));
+            let Some(mut acc) = self.get_account(client_id).await? else {
+                bail!(Rejection::UnknownAccount { client: client_id });
             };
 
+            self.ensure_not_locked(&acc, false)?;
+
             acc.dispute(direction).with_context(|| format!("Failed to perform the dispute for a client_id: {client_id:?} and tx_id: {tx_id:?}"))?;
 
-            self.accounts.upsert(acc).await.with_context(|| {
+            self.call_repository("accounts", "upsert", self.accounts.upsert(acc)).await.with_context(|| {
                 format!("Failed to upsert account in AccountRepo: {acc:?}")
             })
         }
         .await;
 
         if res.is_ok() {
-            return self.transactions.update_status(tx_id, TransactionStatus::Disputed).await.with_context(|| {
+            return self.call_repository("transactions", "update_status", self.transactions.update_status(tx_id, TransactionStatus::Disputed)).await.with_context(|| {
                 format!(
                     "Failed to update transaction status: disputed for client_id: {client_id:?} and tx_id: {tx_id:?}",
                 )
@@ -218,27 +911,28 @@ where
         res
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn handle_resolve(&self, resolve: Resolve, direction: Direction) -> Result<()> {
         let client_id = resolve.client_id;
         let tx_id = resolve.tx_id;
 
         let res = async move {
-            let acc = self.accounts.get(client_id).await;
-
-            let Ok(Some(mut acc)) = acc else {
-                return Err(anyhow!("Failed to get account from AccountRepo for client_id: {client_id:?}"));
+            let Some(mut acc) = self.get_account(client_id).await? else {
+                bail!(Rejection::UnknownAccount { client: client_id });
             };
 
+            self.ensure_not_locked(&acc, false)?;
+
             acc.resolve(direction).with_context(|| format!("Failed to perform the resolve for a client_id: {client_id:?} and tx_id: {tx_id:?}"))?;
 
-            self.accounts.upsert(acc).await.with_context(|| {
+            self.call_repository("accounts", "upsert", self.accounts.upsert(acc)).await.with_context(|| {
                 format!("Failed to upsert account in AccountRepo: {acc:?}")
             })
         }
         .await;
 
         if res.is_ok() {
-            return self.transactions.update_status(tx_id, TransactionStatus::Resolved).await.with_context(|| {
+            return self.call_repository("transactions", "update_status", self.transactions.update_status(tx_id, TransactionStatus::Resolved)).await.with_context(|| {
                 format!(
                     "Failed to update transaction status: resolve for client_id: {client_id:?} and tx_id: {tx_id:?}",
                 )
@@ -248,27 +942,28 @@ where
         res
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn handle_chargeback(&self, cb: Chargeback, direction: Direction) -> Result<()> {
         let client_id = cb.client_id;
         let tx_id = cb.tx_id;
 
         let res = async move {
-            let acc = self.accounts.get(client_id).await;
-
-            let Ok(Some(mut acc)) = acc else {
-                return Err(anyhow!("Failed to get account from AccountRepo for client_id: {client_id:?}"));
+            let Some(mut acc) = self.get_account(client_id).await? else {
+                bail!(Rejection::UnknownAccount { client: client_id });
             };
 
+            self.ensure_not_locked(&acc, false)?;
+
             acc.chargeback(direction).with_context(|| format!("Failed to perform the chargeback for a client_id: {client_id:?} and tx_id: {tx_id:?}"))?;
 
-            self.accounts.upsert(acc).await.with_context(|| {
+            self.call_repository("accounts", "upsert", self.accounts.upsert(acc)).await.with_context(|| {
                 format!("Failed to upsert account in AccountRepo: {acc:?}")
             })
         }
         .await;
 
         if res.is_ok() {
-            return self.transactions.update_status(tx_id, TransactionStatus::Chargedback).await.with_context(|| {
+            return self.call_repository("transactions", "update_status", self.transactions.update_status(tx_id, TransactionStatus::Chargedback)).await.with_context(|| {
                 format!(
                     "Failed to update transaction status: resolve for client_id: {client_id:?} and tx_id: {tx_id:?}",
                 )
@@ -279,34 +974,97 @@ where
     }
 }
 
-fn prevent_replay_attack(maybe_tx: Option<&Transaction>) -> Result<()> {
-    if let Some(tx) = maybe_tx
-        && tx.status == TransactionStatus::Processed
+/// The span of a record, see [PaymentEngine::process_numbered] for the line number.
+fn record_span(tx: &TxRecord, line: Option<u64>) -> Span {
+    info_span!(
+        "record",
+        client = tx.client_id().into_inner(),
+        tx = tx.tx_id().into_inner(),
+        r#type = tx.type_name(),
+        line
+    )
+}
+
+/// Posts an applied record. The postings are derived from the referenced transaction, as it was before the record has been applied.
+async fn post_to_journal(
+    journal: &dyn JournalRepository,
+    tx: TxRecord,
+    referenced: Option<&Transaction>,
+) -> Result<()> {
+    let postings = postings_for(&tx, referenced)?;
+
+    journal
+        .post(tx, postings)
+        .await
+        .context("Failed to post the TxRecord to the journal")?;
+
+    Ok(())
+}
+
+/// Every reuse of a tx id is considered a duplicate, regardless of the status of the stored transaction.
+/// The only exception is a failed transaction, that may be retried by the same client, if the [IdempotencyPolicy] allows it.
+/// Otherwise another client could take over the failed tx id and its transaction would be stored under the id of the original client.
+fn prevent_replay_attack(
+    maybe_tx: Option<&Transaction>,
+    client_id: ClientId,
+    policy: IdempotencyPolicy,
+) -> Result<()> {
+    let Some(tx) = maybe_tx else {
+        return Ok(());
+    };
+
+    if tx.status == TransactionStatus::Failed
+        && tx.client_id == client_id
+        && policy == IdempotencyPolicy::RetryFailed
     {
-        let tx_id = tx.id;
-        let client = tx.client_id;
+        return Ok(());
+    }
+
+    bail!(Rejection::Duplicate {
+        tx: tx.id,
+        status: tx.status
+    })
+}
+
+fn prevent_expired_replay(tx_id: TransactionId, expired: bool) -> Result<()> {
+    if expired {
+        bail!(Rejection::ExpiredDuplicate { tx: tx_id })
+    }
+
+    Ok(())
+}
 
-        bail!(
-            "Failed to process tx: {tx_id:?} for client {client:?} because the referenced transaction either does not exist or does not have the required status: TransactionStatus::Processed"
-        )
+fn ensure_within_dispute_window(tx_id: TransactionId, expired: bool) -> Result<()> {
+    if expired {
+        bail!(Rejection::DisputeWindowExpired { tx: tx_id })
     }
 
     Ok(())
 }
 
-fn tx_exists_and_has_been_processed(maybe_tx: Option<&Transaction>) -> Result<&Transaction> {
-    ensure_tx_and_status(maybe_tx, TransactionStatus::Processed)
+fn tx_exists_and_has_been_processed(
+    tx_id: TransactionId,
+    maybe_tx: Option<&Transaction>,
+) -> Result<&Transaction> {
+    ensure_tx_and_status(tx_id, maybe_tx, TransactionStatus::Processed)
 }
 
-fn tx_exists_and_has_been_disputed(maybe_tx: Option<&Transaction>) -> Result<&Transaction> {
-    ensure_tx_and_status(maybe_tx, TransactionStatus::Disputed)
+fn tx_exists_and_has_been_disputed(
+    tx_id: TransactionId,
+    maybe_tx: Option<&Transaction>,
+) -> Result<&Transaction> {
+    ensure_tx_and_status(tx_id, maybe_tx, TransactionStatus::Disputed)
 }
 
-fn tx_exists_and_has_been_resolved(maybe_tx: Option<&Transaction>) -> Result<&Transaction> {
-    ensure_tx_and_status(maybe_tx, TransactionStatus::Resolved)
+fn tx_exists_and_has_been_resolved(
+    tx_id: TransactionId,
+    maybe_tx: Option<&Transaction>,
+) -> Result<&Transaction> {
+    ensure_tx_and_status(tx_id, maybe_tx, TransactionStatus::Resolved)
 }
 
 fn ensure_tx_and_status(
+    tx_id: TransactionId,
     maybe_tx: Option<&Transaction>,
     expected_status: TransactionStatus,
 ) -> Result<&Transaction> {
@@ -315,8 +1073,9 @@ fn ensure_tx_and_status(
     {
         Ok(tx)
     } else {
-        Err(anyhow!(
-            "Failed to process tx because the referenced transaction either does not exist or failed"
-        ))
+        Err(anyhow!(Rejection::InvalidReference {
+            tx: tx_id,
+            expected: expected_status
+        }))
     }
 }
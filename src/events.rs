@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::transaction::{TransactionId, TxRecord};
+
+/// A change of an account, published by the engine after a record has been applied, see [crate::prelude::PaymentEngine::subscribe].
+///
+/// Every event carries the tx id of the record, that caused it, and the resulting account. It serializes with the `event` as tag,
+/// e.g. `{"event":"balance_changed","tx":1,"account":{"client":1,"available":"1.5",...}}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AccountEvent {
+    /// A deposit or withdrawal changed the balance
+    BalanceChanged {
+        tx: TransactionId,
+        account: Account,
+    },
+    DisputeOpened {
+        tx: TransactionId,
+        account: Account,
+    },
+    DisputeResolved {
+        tx: TransactionId,
+        account: Account,
+    },
+    ChargedBack {
+        tx: TransactionId,
+        account: Account,
+    },
+    /// Published right after [AccountEvent::ChargedBack], if the chargeback locked the account
+    AccountLocked {
+        tx: TransactionId,
+        account: Account,
+    },
+}
+
+impl AccountEvent {
+    /// The events of an applied record. The account before the record is only needed for telling, whether a chargeback locked the account.
+    pub(crate) fn for_record(
+        record: &TxRecord,
+        before: Option<&Account>,
+        after: Account,
+    ) -> Vec<AccountEvent> {
+        let tx = record.tx_id();
+        let account = after;
+
+        match record {
+            TxRecord::Deposit(_) | TxRecord::Withdrawal(_) => {
+                vec![AccountEvent::BalanceChanged { tx, account }]
+            }
+            TxRecord::Dispute(_) => vec![AccountEvent::DisputeOpened { tx, account }],
+            TxRecord::Resolve(_) => vec![AccountEvent::DisputeResolved { tx, account }],
+            TxRecord::Chargeback(_) => {
+                let was_locked = before.is_some_and(|acc| acc.is_locked);
+                let mut events = vec![AccountEvent::ChargedBack { tx, account }];
+                if account.is_locked && !was_locked {
+                    events.push(AccountEvent::AccountLocked { tx, account });
+                }
+                events
+            }
+        }
+    }
+
+    pub fn client_id(&self) -> ClientId {
+        self.account().client_id
+    }
+
+    pub fn account(&self) -> &Account {
+        match self {
+            AccountEvent::BalanceChanged { account, .. }
+            | AccountEvent::DisputeOpened { account, .. }
+            | AccountEvent::DisputeResolved { account, .. }
+            | AccountEvent::ChargedBack { account, .. }
+            | AccountEvent::AccountLocked { account, .. } => account,
+        }
+    }
+}
+
+/// Posts every event as JSON to the configured URL, e.g. of a local service, that reacts on account changes.
+///
+/// A failed delivery, i.e. a connection error or a non-success status, is retried with an exponential backoff.
+/// After the last retry, the event is dropped with a warning, thus a broken receiver doesn't hold back the subsequent events.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: Url,
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Webhook {
+    pub const DEFAULT_RETRIES: u32 = 3;
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(100);
+
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: Client::new(),
+            retries: Self::DEFAULT_RETRIES,
+            backoff: Self::DEFAULT_BACKOFF,
+        }
+    }
+
+    /// The number of retries after the first failed attempt
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The delay before the first retry. It doubles with every further retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Delivers a single event, retrying a failed attempt. Returns the error of the last attempt.
+    pub async fn deliver(&self, event: &AccountEvent) -> Result<()> {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+
+        loop {
+            let res = self.post(event).await;
+            if res.is_ok() || attempt == self.retries {
+                return res;
+            }
+
+            attempt += 1;
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2);
+        }
+    }
+
+    async fn post(&self, event: &AccountEvent) -> Result<()> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(event)
+            .send()
+            .await
+            .with_context(|| format!("Failed to post the event to: {}", self.url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "Failed to deliver the event, because {} answered with: {status}",
+                self.url
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Delivers the events of the receiver one after another, until the engine has been dropped.
+    pub fn spawn(self, mut events: broadcast::Receiver<AccountEvent>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if let Err(err) = self.deliver(&event).await {
+                            warn!("Dropped the event: {event:?} after all retries: {err:?}");
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("The webhook fell behind and dropped {missed} events");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::dec;
+
+    use super::*;
+    use crate::models::transaction::{Chargeback, Deposit};
+
+    fn locked_account() -> Account {
+        Account {
+            is_locked: true,
+            ..Account::new(ClientId::new(1))
+        }
+    }
+
+    fn chargeback() -> TxRecord {
+        TxRecord::Chargeback(Chargeback {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+        })
+    }
+
+    #[test]
+    fn chargeback_publishes_the_lock() {
+        let before = Account::new(ClientId::new(1));
+
+        let events = AccountEvent::for_record(&chargeback(), Some(&before), locked_account());
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                AccountEvent::ChargedBack { .. },
+                AccountEvent::AccountLocked { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn chargeback_of_a_locked_account_doesnt_publish_the_lock_again() {
+        let before = locked_account();
+
+        let events = AccountEvent::for_record(&chargeback(), Some(&before), locked_account());
+
+        assert!(matches!(
+            events.as_slice(),
+            [AccountEvent::ChargedBack { .. }]
+        ));
+    }
+
+    #[test]
+    fn event_is_serialized_with_tag() {
+        let record = TxRecord::Deposit(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(7),
+            amount: crate::models::NonNegativeDecimal::try_from(dec!(1.5)).unwrap(),
+        });
+        let account = Account {
+            available: dec!(1.5),
+            total: dec!(1.5),
+            ..Account::new(ClientId::new(1))
+        };
+
+        let events = AccountEvent::for_record(&record, None, account);
+        let json = serde_json::to_string(&events[0]).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"event":"balance_changed","tx":7,"account":{"client":1,"available":"1.5","held":"0","total":"1.5","locked":false}}"#
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::pin::Pin;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use futures::stream::{Stream, StreamExt};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::engine::{PaymentEngine, RecordOutcome};
+use crate::events::AccountEvent;
+use crate::models::NonNegativeDecimal;
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::rejection::Rejection;
+use crate::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+};
+use crate::repository::account::AccountRepository;
+use crate::repository::transaction::TransactionRepository;
+
+/// The generated types of `proto/payment_engine.proto`
+pub mod proto {
+    tonic::include_proto!("payment_engine.v1");
+}
+
+use proto::payment_engine_server::{PaymentEngine as PaymentEngineRpc, PaymentEngineServer};
+
+/// Wraps the engine as gRPC service, see `proto/payment_engine.proto`.
+///
+/// Like the HTTP API, records are processed one at a time. `WatchAccounts` streams the accounts of the [AccountEvent]s of the engine.
+pub struct GrpcService<AR, TR> {
+    engine: PaymentEngine<AR, TR>,
+    processing: Mutex<()>,
+}
+
+/// Constructs the tonic server of the service, e.g. for [tonic::transport::Server::add_service].
+pub fn grpc_server<AR, TR>(
+    engine: PaymentEngine<AR, TR>,
+) -> PaymentEngineServer<GrpcService<AR, TR>>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    PaymentEngineServer::new(GrpcService {
+        engine,
+        processing: Mutex::new(()),
+    })
+}
+
+impl<AR, TR> GrpcService<AR, TR>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    async fn process(&self, record: TxRecord) -> RecordOutcome {
+        let _guard = self.processing.lock().await;
+
+        self.engine.submit(record).await
+    }
+}
+
+type AccountFeed = Pin<Box<dyn Stream<Item = Result<proto::Account, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl<AR, TR> PaymentEngineRpc for GrpcService<AR, TR>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    async fn submit(
+        &self,
+        request: Request<proto::TxRecord>,
+    ) -> Result<Response<proto::Outcome>, Status> {
+        let record = TxRecord::try_from(request.into_inner())
+            .map_err(|err| Status::invalid_argument(format!("{err:#}")))?;
+
+        let outcome = self.process(record).await;
+
+        Ok(Response::new(outcome.into()))
+    }
+
+    async fn submit_batch(
+        &self,
+        request: Request<Streaming<proto::TxRecord>>,
+    ) -> Result<Response<proto::BatchOutcome>, Status> {
+        let mut records = request.into_inner();
+        let mut outcomes = Vec::new();
+
+        while let Some(record) = records.message().await? {
+            let outcome = match TxRecord::try_from(record) {
+                Ok(record) => self.process(record).await.into(),
+                Err(err) => proto::Outcome {
+                    outcome: Some(proto::outcome::Outcome::Malformed(proto::Malformed {
+                        message: format!("{err:#}"),
+                    })),
+                },
+            };
+            outcomes.push(outcome);
+        }
+
+        Ok(Response::new(proto::BatchOutcome { outcomes }))
+    }
+
+    type WatchAccountsStream = AccountFeed;
+
+    async fn watch_accounts(
+        &self,
+        request: Request<proto::WatchAccountsRequest>,
+    ) -> Result<Response<Self::WatchAccountsStream>, Status> {
+        let client = request
+            .into_inner()
+            .client
+            .map(client_id)
+            .transpose()
+            .map_err(|err| Status::invalid_argument(format!("{err:#}")))?;
+
+        let feed = BroadcastStream::new(self.engine.subscribe())
+            .filter(move |event| {
+                let is_watched = match (event, client) {
+                    // The lock carries the same account as the preceding chargeback
+                    (Ok(AccountEvent::AccountLocked { .. }), _) => false,
+                    (Ok(event), Some(client)) => event.client_id() == client,
+                    _ => true,
+                };
+                futures::future::ready(is_watched)
+            })
+            .map(|event| match event {
+                Ok(event) => Ok(proto::Account::from(*event.account())),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => Err(Status::data_loss(format!(
+                    "The subscriber fell behind and missed {missed} account events"
+                ))),
+            })
+            // A lagging subscriber would otherwise receive an incomplete feed
+            .scan(false, |failed, change| {
+                let item = (!*failed).then_some(change);
+                *failed = item.as_ref().is_some_and(Result::is_err);
+                futures::future::ready(item)
+            });
+
+        Ok(Response::new(Box::pin(feed)))
+    }
+}
+
+fn client_id(client: u32) -> Result<ClientId> {
+    u16::try_from(client)
+        .map(ClientId::new)
+        .with_context(|| format!("The client id: {client} exceeds the range of u16"))
+}
+
+fn amount(amount: &str) -> Result<NonNegativeDecimal> {
+    let amount = Decimal::from_str(amount)
+        .with_context(|| format!("Failed to parse the amount: {amount:?} as decimal"))?;
+
+    NonNegativeDecimal::try_from(amount)
+}
+
+impl TryFrom<proto::TxRecord> for TxRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(record: proto::TxRecord) -> Result<Self> {
+        use proto::tx_record::Record;
+
+        let record = match record.record.context("The record has no type")? {
+            Record::Deposit(deposit) => TxRecord::Deposit(Deposit {
+                client_id: client_id(deposit.client)?,
+                tx_id: TransactionId::new(deposit.tx),
+                amount: amount(&deposit.amount)?,
+            }),
+            Record::Withdrawal(withdrawal) => TxRecord::Withdrawal(Withdrawal {
+                client_id: client_id(withdrawal.client)?,
+                tx_id: TransactionId::new(withdrawal.tx),
+                amount: amount(&withdrawal.amount)?,
+            }),
+            Record::Dispute(dispute) => TxRecord::Dispute(Dispute {
+                client_id: client_id(dispute.client)?,
+                tx_id: TransactionId::new(dispute.tx),
+            }),
+            Record::Resolve(resolve) => TxRecord::Resolve(Resolve {
+                client_id: client_id(resolve.client)?,
+                tx_id: TransactionId::new(resolve.tx),
+            }),
+            Record::Chargeback(chargeback) => TxRecord::Chargeback(Chargeback {
+                client_id: client_id(chargeback.client)?,
+                tx_id: TransactionId::new(chargeback.tx),
+            }),
+        };
+
+        Ok(record)
+    }
+}
+
+impl From<Account> for proto::Account {
+    fn from(account: Account) -> Self {
+        proto::Account {
+            client: account.client_id.into_inner().into(),
+            available: account.available.to_string(),
+            held: account.held.to_string(),
+            total: account.total.to_string(),
+            locked: account.is_locked,
+        }
+    }
+}
+
+impl TryFrom<proto::Account> for Account {
+    type Error = anyhow::Error;
+
+    fn try_from(account: proto::Account) -> Result<Self> {
+        let decimal = |value: &str| {
+            Decimal::from_str(value)
+                .with_context(|| format!("Failed to parse the balance: {value:?} as decimal"))
+        };
+
+        Ok(Account {
+            client_id: client_id(account.client)?,
+            available: decimal(&account.available)?,
+            held: decimal(&account.held)?,
+            total: decimal(&account.total)?,
+            is_locked: account.locked,
+        })
+    }
+}
+
+impl From<RecordOutcome> for proto::Outcome {
+    fn from(outcome: RecordOutcome) -> Self {
+        use proto::outcome::Outcome;
+
+        let outcome = match outcome {
+            RecordOutcome::Applied { account } => Outcome::Applied(proto::Applied {
+                account: account.map(proto::Account::from),
+            }),
+            RecordOutcome::Rejected { rejection, message } => Outcome::Rejected(proto::Rejected {
+                reason: proto::RejectionReason::from(rejection).into(),
+                message,
+            }),
+            RecordOutcome::Failed { message } => Outcome::Failed(proto::Failed { message }),
+        };
+
+        proto::Outcome {
+            outcome: Some(outcome),
+        }
+    }
+}
+
+impl From<Rejection> for proto::RejectionReason {
+    fn from(rejection: Rejection) -> Self {
+        match rejection {
+            Rejection::Duplicate { .. } => Self::Duplicate,
+            Rejection::ExpiredDuplicate { .. } => Self::ExpiredDuplicate,
+            Rejection::NegativeAmount { .. } => Self::NegativeAmount,
+            Rejection::PrecisionExceeded { .. } => Self::PrecisionExceeded,
+            Rejection::MaxAmountExceeded { .. } => Self::MaxAmountExceeded,
+            Rejection::UnknownAccount { .. } => Self::UnknownAccount,
+            Rejection::AccountLocked { .. } => Self::AccountLocked,
+            Rejection::InsufficientFunds { .. } => Self::InsufficientFunds,
+            Rejection::InvalidReference { .. } => Self::InvalidReference,
+            Rejection::NotDisputable { .. } => Self::NotDisputable,
+            Rejection::DisputeWindowExpired { .. } => Self::DisputeWindowExpired,
+            Rejection::LimitExceeded { .. } => Self::LimitExceeded,
+            Rejection::PolicyRejected { .. } => Self::PolicyRejected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use rust_decimal::dec;
+
+    use super::*;
+
+    fn deposit(client: u32, amount: &str) -> proto::TxRecord {
+        proto::TxRecord {
+            record: Some(proto::tx_record::Record::Deposit(proto::Deposit {
+                client,
+                tx: 1,
+                amount: amount.to_owned(),
+            })),
+        }
+    }
+
+    #[test]
+    fn converts_a_valid_record() {
+        let record = assert_ok!(TxRecord::try_from(deposit(1, "1.5")));
+
+        let TxRecord::Deposit(deposit) = record else {
+            panic!("Expected a deposit, but got: {record:?}");
+        };
+        assert_eq!(deposit.amount.into_inner(), dec!(1.5));
+    }
+
+    #[test]
+    fn rejects_invalid_records() {
+        assert_err!(TxRecord::try_from(deposit(70_000, "1")));
+        assert_err!(TxRecord::try_from(deposit(1, "-1")));
+        assert_err!(TxRecord::try_from(deposit(1, "one")));
+        assert_err!(TxRecord::try_from(proto::TxRecord { record: None }));
+    }
+
+    #[test]
+    fn account_roundtrips() {
+        let account = Account {
+            available: dec!(1.25),
+            total: dec!(1.25),
+            ..Account::new(ClientId::new(3))
+        };
+
+        let converted = assert_ok!(Account::try_from(proto::Account::from(account)));
+
+        assert_eq!(converted, account);
+    }
+}
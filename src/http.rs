@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::engine::{PaymentEngine, RecordOutcome};
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::transaction::{Transaction, TransactionId, TxRecord};
+use crate::repository::Page;
+use crate::repository::account::{AccountQuery, AccountRepository};
+use crate::repository::transaction::TransactionRepository;
+
+/// The upper bound of the `limit` of `GET /accounts`, so a single request can't ask for all accounts at once.
+const MAX_LIMIT: usize = 1000;
+
+/// Exposes the engine as HTTP API with JSON bodies:
+///
+/// - `POST /transactions` processes a single [TxRecord] or an array of them
+/// - `GET /accounts/{client}` returns the account of the client
+/// - `GET /accounts` returns a page of the accounts, filtered by the query parameters of an [AccountQuery]
+/// - `GET /transactions/{tx}` returns the stored transaction
+/// - `GET /metrics` returns the metrics in the Prometheus text format, if the engine has been built with [crate::prelude::EngineMetrics]
+///
+/// Every processed record is answered with its [RecordOutcome]. A rejected record carries the typed [crate::models::rejection::Rejection], thus a client can react on the reason instead of parsing the message.
+pub fn http_router<AR, TR>(engine: PaymentEngine<AR, TR>) -> Router
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    let state = Arc::new(AppState {
+        engine,
+        processing: Mutex::new(()),
+    });
+
+    Router::new()
+        .route("/transactions", post(submit::<AR, TR>))
+        .route("/transactions/{tx}", get(get_transaction::<AR, TR>))
+        .route("/accounts", get(list_accounts::<AR, TR>))
+        .route("/accounts/{client}", get(get_account::<AR, TR>))
+        .route("/metrics", get(get_metrics::<AR, TR>))
+        .with_state(state)
+}
+
+struct AppState<AR, TR> {
+    engine: PaymentEngine<AR, TR>,
+    // The engine reads and writes an account in separate steps. Concurrent records of the same client would overwrite each other's update, thus records are processed one at a time.
+    // Reads don't need the lock, because every repository call is atomic on its own.
+    processing: Mutex<()>,
+}
+
+/// The body of `POST /transactions`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Submission {
+    Batch(Vec<TxRecord>),
+    Single(TxRecord),
+}
+
+/// The answer for a single record
+#[derive(Debug, Serialize)]
+struct RecordResponse {
+    record: TxRecord,
+    #[serde(flatten)]
+    outcome: RecordOutcome,
+}
+
+impl RecordOutcome {
+    fn status(&self) -> StatusCode {
+        match self {
+            RecordOutcome::Applied { .. } => StatusCode::OK,
+            RecordOutcome::Rejected { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            RecordOutcome::Failed { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The query parameters of `GET /accounts`, see [AccountQuery]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AccountParams {
+    locked: bool,
+    with_held: bool,
+    total_below: Option<Decimal>,
+    after: Option<u16>,
+    limit: Option<usize>,
+}
+
+impl From<AccountParams> for AccountQuery {
+    fn from(params: AccountParams) -> Self {
+        AccountQuery {
+            locked: params.locked,
+            with_held: params.with_held,
+            total_below: params.total_below,
+            after: params.after.map(ClientId::new),
+            limit: params
+                .limit
+                .unwrap_or(AccountQuery::DEFAULT_LIMIT)
+                .min(MAX_LIMIT),
+        }
+    }
+}
+
+/// The errors of the read endpoints, written as `{"error": "..."}`
+enum ApiError {
+    NotFound(String),
+    BadRequest(String),
+    Internal(anyhow::Error),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Internal(err) => {
+                error!("Failed to handle the request: {err:?}");
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+            }
+        };
+
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+/// A single record is answered with the status of its outcome. A batch is always answered with `200 OK` and the outcome of every record in the same order.
+async fn submit<AR, TR>(
+    State(state): State<Arc<AppState<AR, TR>>>,
+    submission: Result<Json<Submission>, JsonRejection>,
+) -> Result<Response, ApiError>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    let Json(submission) = submission.map_err(|err| ApiError::BadRequest(err.body_text()))?;
+
+    let response = match submission {
+        Submission::Single(record) => {
+            let response = process(&state, record).await;
+
+            (response.outcome.status(), Json(response)).into_response()
+        }
+        Submission::Batch(records) => {
+            let mut responses = Vec::with_capacity(records.len());
+            for record in records {
+                responses.push(process(&state, record).await);
+            }
+
+            (StatusCode::OK, Json(responses)).into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+async fn process<AR, TR>(state: &AppState<AR, TR>, record: TxRecord) -> RecordResponse
+where
+    AR: AccountRepository + Send + Sync,
+    TR: TransactionRepository,
+{
+    let _guard = state.processing.lock().await;
+
+    let outcome = state.engine.submit(record).await;
+
+    RecordResponse { record, outcome }
+}
+
+async fn get_account<AR, TR>(
+    State(state): State<Arc<AppState<AR, TR>>>,
+    Path(client): Path<u16>,
+) -> Result<Json<Account>, ApiError>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    let client_id = ClientId::new(client);
+
+    state
+        .engine
+        .accounts()
+        .get(client_id)
+        .await
+        .map_err(ApiError::Internal)?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("There is no account for client: {client}")))
+}
+
+async fn list_accounts<AR, TR>(
+    State(state): State<Arc<AppState<AR, TR>>>,
+    Query(params): Query<AccountParams>,
+) -> Result<Json<Page<Account, ClientId>>, ApiError>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    let page = state
+        .engine
+        .accounts()
+        .query(&params.into())
+        .await
+        .map_err(ApiError::Internal)?;
+
+    Ok(Json(page))
+}
+
+async fn get_transaction<AR, TR>(
+    State(state): State<Arc<AppState<AR, TR>>>,
+    Path(tx): Path<u32>,
+) -> Result<Json<Transaction>, ApiError>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    state
+        .engine
+        .transactions()
+        .get(TransactionId::new(tx))
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("There is no transaction with tx: {tx}")))
+}
+
+async fn get_metrics<AR, TR>(
+    State(state): State<Arc<AppState<AR, TR>>>,
+) -> Result<Response, ApiError>
+where
+    AR: AccountRepository + Send + Sync + 'static,
+    TR: TransactionRepository + 'static,
+{
+    let metrics = state
+        .engine
+        .encode_metrics()
+        .await
+        .map_err(ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("The metrics are not enabled".to_owned()))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics,
+    )
+        .into_response())
+}
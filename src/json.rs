@@ -0,0 +1,116 @@
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use futures::stream::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::pin;
+
+use crate::models::account::Account;
+use crate::models::ledger::LedgerEntry;
+use crate::models::transaction::Transaction;
+
+/// Encodes the items as a single JSON array. It uses the same field names as the [crate::prelude::CsvEncoder].
+///
+/// Decimals are encoded as strings, because JSON numbers are commonly parsed as floats, which would lose precision.
+/// The array is written incrementally, thus the items never need to be collected in memory.
+pub struct JsonEncoder;
+
+impl JsonEncoder {
+    pub async fn encode_balances<W, S>(sink: W, accounts: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Account>,
+    {
+        Self::encode(sink, accounts)
+            .await
+            .context("Failed to encode the accounts")
+    }
+
+    pub async fn encode_transactions<W, S>(sink: W, transactions: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Transaction>,
+    {
+        Self::encode(sink, transactions)
+            .await
+            .context("Failed to encode the transactions")
+    }
+
+    async fn encode<W, S, T>(mut sink: W, items: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = T>,
+        T: Serialize,
+    {
+        sink.write_all(b"[")
+            .context("Failed to write the JSON array start")?;
+
+        pin!(items);
+        let mut is_first = true;
+        while let Some(item) = items.next().await {
+            if !is_first {
+                sink.write_all(b",")
+                    .context("Failed to write the JSON separator")?;
+            }
+            is_first = false;
+
+            serde_json::to_writer(&mut sink, &item).context("Failed to serialize item as JSON")?;
+        }
+
+        sink.write_all(b"]\n")
+            .context("Failed to write the JSON array end")?;
+        sink.flush().context("Failed to flush the writer")
+    }
+}
+
+/// Encodes each item as JSON object on a dedicated line (JSON Lines). Besides that, it behaves like the [JsonEncoder].
+pub struct JsonLinesEncoder;
+
+impl JsonLinesEncoder {
+    pub async fn encode_balances<W, S>(sink: W, accounts: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Account>,
+    {
+        Self::encode(sink, accounts)
+            .await
+            .context("Failed to encode the accounts")
+    }
+
+    pub async fn encode_transactions<W, S>(sink: W, transactions: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = Transaction>,
+    {
+        Self::encode(sink, transactions)
+            .await
+            .context("Failed to encode the transactions")
+    }
+
+    /// The ledger is only offered as JSON Lines, because the nested before and after accounts don't fit into CSV columns.
+    pub async fn encode_ledger<W, S>(sink: W, entries: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = LedgerEntry>,
+    {
+        Self::encode(sink, entries)
+            .await
+            .context("Failed to encode the ledger")
+    }
+
+    async fn encode<W, S, T>(mut sink: W, items: S) -> Result<()>
+    where
+        W: Write,
+        S: Stream<Item = T>,
+        T: Serialize,
+    {
+        pin!(items);
+        while let Some(item) = items.next().await {
+            serde_json::to_writer(&mut sink, &item).context("Failed to serialize item as JSON")?;
+            sink.write_all(b"\n")
+                .context("Failed to write the line break")?;
+        }
+
+        sink.flush().context("Failed to flush the writer")
+    }
+}
@@ -1,5 +1,20 @@
+mod audit;
+mod bus;
+mod config;
 mod csv;
+mod diff;
 mod engine;
+mod events;
+mod grpc;
+mod http;
+mod json;
+mod limits;
+mod metrics;
 pub mod models;
+mod policy;
 pub mod prelude;
+mod reconcile;
 mod repository;
+mod socket;
+mod summary;
+mod telemetry;
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use rust_decimal::Decimal;
+use tokio::time::Instant;
+
+use crate::config::{LimitsConfig, TxLimits};
+use crate::models::client::ClientId;
+use crate::models::rejection::{Limit, Rejection};
+use crate::models::transaction::TransactionType;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// The applied amounts by client and type, ordered by the time of their application
+type Usage = HashMap<(ClientId, TransactionType), VecDeque<(Instant, Decimal)>>;
+
+/// Tracks the applied deposits and withdrawals of every client, as long as they count towards a limit of [LimitsConfig].
+///
+/// The records of the engine are processed one at a time, thus a record is checked first and only recorded, once it has been applied.
+/// The time of a record is the time of its application, see [LimitsConfig] for the consequences in a batch run.
+pub(crate) struct LimitTracker {
+    config: LimitsConfig,
+    usage: Mutex<Usage>,
+}
+
+impl LimitTracker {
+    pub(crate) fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Usage> {
+        // An amount is either recorded or not, thus the usage is consistent, even if it is poisoned
+        self.usage.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn limits(&self, tx_type: TransactionType) -> Option<&TxLimits> {
+        let limits = match tx_type {
+            TransactionType::Deposit => &self.config.deposit,
+            TransactionType::Withdrawal => &self.config.withdrawal,
+        };
+
+        (!limits.is_unlimited()).then_some(limits)
+    }
+
+    /// Rejects the amount, if it exceeds a limit of the client, given the records applied before.
+    pub(crate) fn check(
+        &self,
+        client: ClientId,
+        tx_type: TransactionType,
+        amount: Decimal,
+    ) -> Result<()> {
+        let Some(limits) = self.limits(tx_type) else {
+            return Ok(());
+        };
+        let exceeded = |limit| Rejection::LimitExceeded { client, limit };
+
+        if let Some(max_amount) = limits.max_amount
+            && amount > max_amount
+        {
+            bail!(exceeded(Limit::MaxAmount));
+        }
+
+        let usage = self.lock();
+        let Some(applied) = usage.get(&(client, tx_type)) else {
+            return Ok(());
+        };
+        let within = |window: Duration| {
+            applied
+                .iter()
+                .rev()
+                .take_while(move |(at, _)| at.elapsed() < window)
+        };
+
+        if let Some(daily_amount) = limits.daily_amount {
+            let sum = within(DAY).fold(amount, |sum, (_, amount)| sum.saturating_add(*amount));
+            if sum > daily_amount {
+                bail!(exceeded(Limit::DailyAmount));
+            }
+        }
+
+        if let Some(velocity) = limits.velocity
+            && within(Duration::from_secs(velocity.window_secs)).count() >= velocity.count
+        {
+            bail!(exceeded(Limit::Velocity));
+        }
+
+        Ok(())
+    }
+
+    /// Records an applied amount and forgets the ones, that don't count towards any limit anymore.
+    pub(crate) fn record(&self, client: ClientId, tx_type: TransactionType, amount: Decimal) {
+        let Some(limits) = self.limits(tx_type) else {
+            return;
+        };
+        let horizon = [
+            limits.daily_amount.map(|_| DAY),
+            limits
+                .velocity
+                .map(|velocity| Duration::from_secs(velocity.window_secs)),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default();
+
+        let mut usage = self.lock();
+        let applied = usage.entry((client, tx_type)).or_default();
+        applied.push_back((Instant::now(), amount));
+        while applied
+            .front()
+            .is_some_and(|(at, _)| at.elapsed() >= horizon)
+        {
+            applied.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use rust_decimal::dec;
+
+    use super::*;
+    use crate::config::Velocity;
+
+    fn limit_of(res: Result<()>) -> Limit {
+        let err = assert_err!(res);
+        match Rejection::of(&err) {
+            Some(Rejection::LimitExceeded { limit, .. }) => *limit,
+            other => panic!("Expected a limit to be exceeded, got: {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn daily_amount_frees_up_after_a_day() {
+        let client = ClientId::new(1);
+        let tracker = LimitTracker::new(LimitsConfig {
+            withdrawal: TxLimits {
+                daily_amount: Some(dec!(100)),
+                ..TxLimits::default()
+            },
+            ..LimitsConfig::default()
+        });
+        tracker.record(client, TransactionType::Withdrawal, dec!(80));
+
+        assert_eq!(
+            limit_of(tracker.check(client, TransactionType::Withdrawal, dec!(30))),
+            Limit::DailyAmount
+        );
+        assert_ok!(tracker.check(client, TransactionType::Withdrawal, dec!(20)));
+        assert_ok!(tracker.check(client, TransactionType::Deposit, dec!(1000)));
+        assert_ok!(tracker.check(ClientId::new(2), TransactionType::Withdrawal, dec!(30)));
+
+        tokio::time::advance(DAY).await;
+
+        assert_ok!(tracker.check(client, TransactionType::Withdrawal, dec!(30)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn velocity_counts_within_the_window() {
+        let client = ClientId::new(1);
+        let tracker = LimitTracker::new(LimitsConfig {
+            deposit: TxLimits {
+                velocity: Some(Velocity {
+                    count: 2,
+                    window_secs: 60,
+                }),
+                ..TxLimits::default()
+            },
+            ..LimitsConfig::default()
+        });
+
+        tracker.record(client, TransactionType::Deposit, dec!(1));
+        tokio::time::advance(Duration::from_secs(30)).await;
+        tracker.record(client, TransactionType::Deposit, dec!(1));
+
+        assert_eq!(
+            limit_of(tracker.check(client, TransactionType::Deposit, dec!(1))),
+            Limit::Velocity
+        );
+
+        tokio::time::advance(Duration::from_secs(31)).await;
+
+        assert_ok!(tracker.check(client, TransactionType::Deposit, dec!(1)));
+    }
+}
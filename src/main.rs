@@ -1,34 +1,779 @@
 use std::fs::File;
-use std::io;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::stream::{StreamExt, TryStreamExt};
+use reqwest::Url;
+use tokio::net::TcpListener;
 
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
 use toy_payment_engine::prelude::*;
 
+/// Processes the CSV encoded transactions of the input file and writes the client account balances as CSV to stdout.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the CSV file containing the transactions
+    #[arg(required = true)]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    engine: EngineArgs,
+
+    /// Order of the output rows
+    #[arg(long, value_enum, default_value_t = SortKey::Client)]
+    sort: SortKey,
+
+    /// Format of the balances written to stdout
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    output_format: OutputFormat,
+
+    /// Writes the transaction history (type, client, tx, amount and final status) to the given file, using the output format
+    #[arg(long)]
+    history: Option<PathBuf>,
+
+    /// Restricts the transaction history to the given client
+    #[arg(long, requires = "history")]
+    history_client: Option<u16>,
+
+    /// Writes the ledger of all processed records, including their outcome and the balances before and after, as JSON Lines to the given file
+    #[arg(long)]
+    ledger: Option<PathBuf>,
+
+    /// Writes a hash-chained audit log of the applied records to the given file. It can be checked by the verify command
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Writes the metrics of the run in the Prometheus text format to the given file
+    #[arg(long)]
+    metrics: Option<PathBuf>,
+
+    /// Writes the summary of the run as JSON to the given file. With `-`, it is printed to stderr instead
+    #[arg(long)]
+    summary: Option<PathBuf>,
+
+    /// Format of the logs on stderr. The events are filtered by RUST_LOG, which defaults to warn
+    #[arg(long, value_enum, global = true, default_value_t = LogFormatArg::Pretty)]
+    log_format: LogFormatArg,
+
+    /// Exports the spans via OTLP/gRPC to a collector, e.g. `http://127.0.0.1:4317`
+    #[arg(long, global = true)]
+    otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Processes the input with a double-entry journal and writes its trial balance as CSV to stdout.
+    /// Fails, if the journal doesn't sum up to zero or its projection differs from the account balances
+    TrialBalance {
+        /// Path to the CSV file containing the transactions
+        input: PathBuf,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+    },
+
+    /// Processes the input and compares the resulting balances with the expected ones. Writes the discrepancies as CSV to stdout.
+    /// Fails, if a client is missing or unexpected, or any field of an account differs
+    Reconcile {
+        /// Path to the CSV file containing the transactions
+        input: PathBuf,
+
+        /// Path to the CSV file containing the expected balances, in the output format of the engine
+        #[arg(long)]
+        expected: PathBuf,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+    },
+
+    /// Compares two balance outputs, e.g. before and after a rule change. Writes the per-client differences as CSV to stdout and a summary to stderr
+    Diff {
+        /// Path to the balances of the baseline
+        baseline: PathBuf,
+
+        /// Path to the balances of the candidate
+        candidate: PathBuf,
+
+        /// Format of both balance outputs
+        #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+        format: OutputFormat,
+    },
+
+    /// Runs the engine as a long-lived HTTP service, accepting records as JSON and exposing the accounts, transactions and metrics
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: SocketAddr,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+
+        #[command(flatten)]
+        webhook: WebhookArgs,
+    },
+
+    /// Runs the engine as a long-lived gRPC service, see `proto/payment_engine.proto`
+    Grpc {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: SocketAddr,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+
+        #[command(flatten)]
+        webhook: WebhookArgs,
+    },
+
+    /// Accepts newline-delimited records from many concurrent producers over TCP and/or a Unix domain socket.
+    /// Every record is acked with its outcome as JSON line on the same connection
+    Listen {
+        /// TCP address to accept producers on
+        #[arg(long, required_unless_present = "unix")]
+        tcp: Option<SocketAddr>,
+
+        /// Path of the Unix domain socket to accept producers on. It must not exist yet
+        #[arg(long)]
+        unix: Option<PathBuf>,
+
+        /// Encoding of the records, one record per line
+        #[arg(long, value_enum, default_value_t = LineFormatArg::Csv)]
+        format: LineFormatArg,
+
+        /// Number of records waiting for the engine, before the producers are slowed down
+        #[arg(long, default_value_t = NonZeroUsize::new(1024).unwrap())]
+        capacity: NonZeroUsize,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+
+        #[command(flatten)]
+        webhook: WebhookArgs,
+    },
+
+    /// Walks the hash chain of an audit log and reports the first broken link. Fails, if the chain is broken
+    Verify {
+        /// Path to the audit log
+        audit_log: PathBuf,
+    },
+}
+
+/// The options of the commands, that run the engine as long-lived service
+#[derive(Debug, Args)]
+struct WebhookArgs {
+    /// URL, that every account event is posted to as JSON, e.g. `http://127.0.0.1:9000/events`
+    #[arg(long)]
+    webhook: Option<Url>,
+
+    /// Number of retries of a failed delivery, before the event is dropped
+    #[arg(long, default_value_t = Webhook::DEFAULT_RETRIES, requires = "webhook")]
+    webhook_retries: u32,
+}
+
+impl WebhookArgs {
+    /// Subscribes the webhook to the events of the engine, thus it must be called before the first record is processed.
+    fn spawn<AR, TR>(&self, engine: &PaymentEngine<AR, TR>)
+    where
+        AR: AccountRepository,
+        TR: TransactionRepository,
+    {
+        if let Some(url) = &self.webhook {
+            Webhook::new(url.clone())
+                .retries(self.webhook_retries)
+                .spawn(engine.subscribe());
+        }
+    }
+}
+
+/// The options shared by every command, that runs the engine
+#[derive(Debug, Args)]
+struct EngineArgs {
+    /// Path to a TOML file configuring the business rules of the engine
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Retains only the given number of most recent transactions for disputes. Older tx ids are still rejected as duplicates
+    #[arg(long, conflicts_with = "retain_secs")]
+    retain_count: Option<usize>,
+
+    /// Retains transactions for disputes only for the given number of seconds. Older tx ids are still rejected as duplicates
+    #[arg(long)]
+    retain_secs: Option<u64>,
+
+    /// Compact index of seen tx ids, replacing the lookup of full transactions in the replay check
+    #[arg(long, value_enum)]
+    seen_index: Option<SeenIndexKind>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Csv,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortKey {
+    /// Ascending by client id
+    Client,
+    /// Descending by total, ties are ordered by client id
+    TotalDesc,
+}
+
+impl From<SortKey> for BalanceOrder {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Client => BalanceOrder::ClientId,
+            SortKey::TotalDesc => BalanceOrder::TotalDesc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LineFormatArg {
+    /// A CSV record without header, e.g. `deposit,1,1,1.5`
+    Csv,
+    /// A JSON object, e.g. `{"type":"deposit","client":1,"tx":1,"amount":"1.5"}`
+    Json,
+}
+
+impl From<LineFormatArg> for LineFormat {
+    fn from(format: LineFormatArg) -> Self {
+        match format {
+            LineFormatArg::Csv => LineFormat::Csv,
+            LineFormatArg::Json => LineFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LogFormatArg {
+    /// Human-readable, together with the spans of the event
+    Pretty,
+    /// One JSON object per event
+    Json,
+    Off,
+}
+
+impl From<LogFormatArg> for LogFormat {
+    fn from(format: LogFormatArg) -> Self {
+        match format {
+            LogFormatArg::Pretty => LogFormat::Pretty,
+            LogFormatArg::Json => LogFormat::Json,
+            LogFormatArg::Off => LogFormat::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SeenIndexKind {
+    Bitmap,
+    Roaring,
+}
+
+type Engine = PaymentEngine<InMemoryAccountRepository, InMemoryTxRepository>;
+
+/// The builder of the engine, together with its repositories for reading the results
+struct Components {
+    builder: PaymentEngineBuilder<InMemoryAccountRepository, InMemoryTxRepository>,
+    accounts: Arc<InMemoryAccountRepository>,
+    transactions: Arc<InMemoryTxRepository>,
+}
+
+impl EngineArgs {
+    fn retention(&self) -> TxRetention {
+        match (self.retain_count, self.retain_secs) {
+            (Some(count), _) => TxRetention::Count(count),
+            (None, Some(secs)) => TxRetention::Duration(Duration::from_secs(secs)),
+            (None, None) => TxRetention::Unbounded,
+        }
+    }
+
+    fn seen_index(&self) -> Option<Arc<dyn SeenTxIndex>> {
+        self.seen_index.map(|kind| -> Arc<dyn SeenTxIndex> {
+            match kind {
+                SeenIndexKind::Bitmap => Arc::new(BitmapSeenIndex::new()),
+                SeenIndexKind::Roaring => Arc::new(RoaringSeenIndex::new()),
+            }
+        })
+    }
+
+    /// Prepares the builder with the repositories and the configuration. The caller adds the optional extensions.
+    fn components(&self) -> Result<Components> {
+        let config = self
+            .config
+            .as_ref()
+            .map(EngineConfig::from_toml_file)
+            .transpose()
+            .context("Failed to load the engine configuration. Exiting...")?
+            .unwrap_or_default();
+
+        let accounts = Arc::new(InMemoryAccountRepository::new());
+        let transactions = Arc::new(InMemoryTxRepository::with_retention(self.retention()));
+        let mut builder =
+            PaymentEngine::builder(Arc::clone(&accounts), Arc::clone(&transactions)).config(config);
+        if let Some(seen_index) = self.seen_index() {
+            builder = builder.seen_index(seen_index);
+        }
+
+        Ok(Components {
+            builder,
+            accounts,
+            transactions,
+        })
+    }
+}
+
+fn open_input(input: &Path) -> Result<CsvDecoder<File>> {
+    let file = File::open(input).with_context(|| {
+        format!(
+            "Failed to open file with path: {}. Exiting",
+            input.display()
+        )
+    })?;
+
+    Ok(CsvDecoder::new(file))
+}
+
+/// Returns the number of rows read and decoded from the file
+async fn process_file(engine: &Engine, input: &Path) -> Result<DecodeStats> {
+    let mut csv_decoder = open_input(input)?;
+    engine
+        .process_numbered(csv_decoder.decode_numbered_tx())
+        .await?;
+
+    Ok(csv_decoder.stats())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<_> = std::env::args().skip(1).collect();
+    let cli = Cli::parse();
+    let telemetry = Telemetry::init(cli.log_format.into(), cli.otlp_endpoint.as_deref())?;
+
+    let res = execute(&cli).await;
+    // The result of the command takes precedence over a failed export
+    if let Err(err) = telemetry.shutdown() {
+        eprintln!("{err:?}");
+    }
+
+    res
+}
+
+async fn execute(cli: &Cli) -> Result<()> {
+    match &cli.command {
+        Some(Command::TrialBalance { input, engine }) => trial_balance(input, engine).await,
+        Some(Command::Reconcile {
+            input,
+            expected,
+            engine,
+        }) => reconcile_balances(input, expected, engine).await,
+        Some(Command::Diff {
+            baseline,
+            candidate,
+            format,
+        }) => diff_balances(baseline, candidate, *format).await,
+        Some(Command::Serve {
+            addr,
+            engine,
+            webhook,
+        }) => serve(*addr, engine, webhook).await,
+        Some(Command::Grpc {
+            addr,
+            engine,
+            webhook,
+        }) => serve_grpc(*addr, engine, webhook).await,
+        Some(Command::Listen {
+            tcp,
+            unix,
+            format,
+            capacity,
+            engine,
+            webhook,
+        }) => {
+            listen(
+                *tcp,
+                unix.as_deref(),
+                (*format).into(),
+                *capacity,
+                engine,
+                webhook,
+            )
+            .await
+        }
+        Some(Command::Verify { audit_log }) => verify_audit_log(audit_log),
+        None => run(cli).await,
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    let input = cli.input.as_ref().context("Missing the input file")?;
+    let Components {
+        mut builder,
+        accounts,
+        transactions,
+    } = cli.engine.components()?;
+
+    let ledger = Arc::new(InMemoryLedger::new());
+    if cli.ledger.is_some() {
+        builder = builder.ledger(Arc::clone(&ledger) as Arc<dyn LedgerRepository>);
+    }
+    let audit_log = match &cli.audit_log {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create audit log: {}", path.display()))?;
+            let audit_log: Arc<dyn AuditSink> = Arc::new(AuditLog::new(BufWriter::new(file)));
+            builder = builder.audit_log(Arc::clone(&audit_log));
+
+            Some(audit_log)
+        }
+        None => None,
+    };
+    if cli.metrics.is_some() {
+        builder = builder.metrics(Arc::new(EngineMetrics::new()?));
+    }
+    if cli.summary.is_some() {
+        builder = builder.run_stats(Arc::new(RunStats::new()));
+    }
+    let engine = builder.build();
+
+    let mut csv_decoder = open_input(input)?;
+    let processing = engine
+        .process_numbered(csv_decoder.decode_numbered_tx())
+        .await;
+
+    // A strict run is summarized as well, up to the aborting record
+    if let Some(path) = &cli.summary
+        && let Some(summary) = engine.run_summary(csv_decoder.stats()).await?
+    {
+        match path == Path::new("-") {
+            true => print_summary(&summary),
+            false => write_summary(path, &summary)?,
+        }
+    }
+    processing?;
+
+    if let Some(audit_log) = audit_log {
+        audit_log.flush()?;
+    }
 
-    let file_path = args
-        .first()
-        .context("Expected the input file path as first argument. Exiting...")?;
-    let file = File::open(file_path)
-        .with_context(|| format!("Failed to open file with path: {}. Exiting", file_path))?;
+    if let Some(path) = &cli.metrics
+        && let Some(metrics) = engine.encode_metrics().await?
+    {
+        std::fs::write(path, metrics)
+            .with_context(|| format!("Failed to write metrics file: {}", path.display()))?;
+    }
 
-    let mut csv_decoder = CsvDecoder::new(file);
-    let accounts = Arc::new(InMemoryAccountRepository::new());
-    let transactions = Arc::new(InMemoryTxRepository::new());
-    let engine = PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions));
+    if let Some(path) = &cli.history {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create history file: {}", path.display()))?;
+        let sink = BufWriter::new(file);
+        let history = transactions.history(cli.history_client.map(ClientId::new));
 
-    engine.process(csv_decoder.decode_tx()).await;
+        match cli.output_format {
+            OutputFormat::Csv => CsvEncoder::encode_transactions(sink, history).await,
+            OutputFormat::Json => JsonEncoder::encode_transactions(sink, history).await,
+            OutputFormat::Jsonl => JsonLinesEncoder::encode_transactions(sink, history).await,
+        }
+        .context("Failed to encode the transaction history")?;
+    }
 
-    // Using an allocated vector here, due to time constraints
-    // Ideally, streaming the accounts into the CsvEncoder would be better
-    let balances = accounts.balances().await;
+    if let Some(path) = &cli.ledger {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create ledger file: {}", path.display()))?;
 
-    CsvEncoder::encode_balances(io::stdout(), &balances)
-        .context("Failed to encode balances as Csv")?;
+        JsonLinesEncoder::encode_ledger(BufWriter::new(file), ledger.entries(None))
+            .await
+            .context("Failed to encode the ledger")?;
+    }
+
+    let sink = io::stdout().lock();
+    let balances = accounts.balances(cli.sort.into());
+    match cli.output_format {
+        OutputFormat::Csv => CsvEncoder::encode_balances(sink, balances)
+            .await
+            .context("Failed to encode balances as Csv")?,
+        OutputFormat::Json => JsonEncoder::encode_balances(sink, balances)
+            .await
+            .context("Failed to encode balances as JSON")?,
+        OutputFormat::Jsonl => JsonLinesEncoder::encode_balances(sink, balances)
+            .await
+            .context("Failed to encode balances as JSON Lines")?,
+    }
 
     Ok(())
 }
+
+fn write_summary(path: &Path, summary: &RunSummary) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create summary file: {}", path.display()))?;
+    let mut sink = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut sink, summary)
+        .context("Failed to encode the summary as JSON")?;
+    sink.flush().context("Failed to write the summary")
+}
+
+fn print_summary(summary: &RunSummary) {
+    let records = &summary.records;
+    eprintln!(
+        "records: {} read, {} decoded, {} applied, {} rejected, {} failed",
+        records.read, records.decoded, records.applied, records.rejected, records.failed
+    );
+    for (reason, count) in &summary.rejections {
+        eprintln!("rejected as {reason}: {count}");
+    }
+    for (sink, count) in &summary.sink_failures {
+        eprintln!("failed to write to the {sink}: {count}");
+    }
+    for (tx_type, types) in &summary.types {
+        eprintln!(
+            "{tx_type}: {} records, {} applied, amount: {}",
+            types.count, types.applied, types.amount
+        );
+    }
+    eprintln!(
+        "accounts: {} created, {} locked",
+        summary.accounts_created, summary.accounts_locked
+    );
+    eprintln!("open disputes: {}", summary.open_disputes);
+    eprintln!(
+        "elapsed: {:.3}s, throughput: {:.0} records/s",
+        summary.elapsed_secs, summary.throughput
+    );
+    if let Some(abort) = &summary.aborted {
+        match abort.line {
+            Some(line) => eprintln!("aborted at line {line}: {}", abort.error),
+            None => eprintln!("aborted: {}", abort.error),
+        }
+    }
+}
+
+async fn trial_balance(input: &Path, args: &EngineArgs) -> Result<()> {
+    let Components {
+        builder, accounts, ..
+    } = args.components()?;
+    let journal = Arc::new(InMemoryJournal::new());
+    let engine = builder
+        .journal(Arc::clone(&journal) as Arc<dyn JournalRepository>)
+        .build();
+
+    process_file(&engine, input).await?;
+
+    let trial_balance = journal.trial_balance().await?;
+    CsvEncoder::encode_trial_balance(io::stdout().lock(), &trial_balance).await?;
+
+    if !trial_balance.is_balanced() {
+        bail!(
+            "The journal is not balanced, its sub-ledgers sum up to: {}",
+            trial_balance.sum()
+        );
+    }
+
+    // Empty accounts, that have never been posted to, are not part of the projection. Thus, empty accounts are ignored on both sides
+    let is_empty = |acc: &Account| *acc == Account::new(acc.client_id);
+    let mut projected = journal.accounts().await?;
+    projected.retain(|acc| !is_empty(acc));
+    let balances: Vec<Account> = accounts
+        .balances(BalanceOrder::ClientId)
+        .filter(|acc| future::ready(!is_empty(acc)))
+        .collect()
+        .await;
+    if projected != balances {
+        bail!("The account balances differ from the projection of the journal");
+    }
+
+    Ok(())
+}
+
+async fn reconcile_balances(input: &Path, expected: &Path, args: &EngineArgs) -> Result<()> {
+    let file = File::open(expected)
+        .with_context(|| format!("Failed to open expected balances: {}", expected.display()))?;
+    let expected: Vec<Account> = CsvDecoder::new(file)
+        .decode_balances()
+        .try_collect()
+        .await
+        .context("Failed to decode the expected balances")?;
+
+    let Components {
+        builder, accounts, ..
+    } = args.components()?;
+    let engine = builder.build();
+
+    process_file(&engine, input).await?;
+
+    let actual: Vec<Account> = accounts.balances(BalanceOrder::ClientId).collect().await;
+    let reconciliation = reconcile(expected, actual);
+    CsvEncoder::encode_reconciliation(io::stdout().lock(), &reconciliation).await?;
+
+    if !reconciliation.is_reconciled() {
+        bail!(
+            "The balances don't reconcile, found {} discrepancies",
+            reconciliation.discrepancies.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn diff_balances(baseline: &Path, candidate: &Path, format: OutputFormat) -> Result<()> {
+    let baseline = read_balances(baseline, format).await?;
+    let candidate = read_balances(candidate, format).await?;
+
+    let BalanceDiff {
+        reconciliation,
+        summary,
+    } = diff(baseline, candidate);
+    CsvEncoder::encode_reconciliation(io::stdout().lock(), &reconciliation).await?;
+
+    eprintln!("clients: {}", summary.clients);
+    eprintln!("changed: {}", summary.changed);
+    eprintln!("only in baseline: {}", summary.only_in_baseline);
+    eprintln!("only in candidate: {}", summary.only_in_candidate);
+    eprintln!("duplicated: {}", summary.duplicated);
+    eprintln!(
+        "total: {} -> {}",
+        summary.baseline_total, summary.candidate_total
+    );
+    eprintln!(
+        "locked: {} -> {}",
+        summary.baseline_locked, summary.candidate_locked
+    );
+
+    Ok(())
+}
+
+/// Reads balances in any of the output formats of the engine
+async fn read_balances(path: &Path, format: OutputFormat) -> Result<Vec<Account>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open balances: {}", path.display()))?;
+
+    match format {
+        OutputFormat::Csv => CsvDecoder::new(file).decode_balances().try_collect().await,
+        OutputFormat::Json => {
+            serde_json::from_reader(BufReader::new(file)).context("Failed to decode the JSON array")
+        }
+        OutputFormat::Jsonl => BufReader::new(file)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| {
+                let line = line.context("Failed to read a line")?;
+                serde_json::from_str(&line).context("Failed to decode the JSON line")
+            })
+            .collect(),
+    }
+    .with_context(|| format!("Failed to decode balances: {}", path.display()))
+}
+
+async fn serve(addr: SocketAddr, args: &EngineArgs, webhook: &WebhookArgs) -> Result<()> {
+    let Components { builder, .. } = args.components()?;
+    let engine = builder.metrics(Arc::new(EngineMetrics::new()?)).build();
+    webhook.spawn(&engine);
+    let router = http_router(engine);
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind to address: {addr}"))?;
+    eprintln!("Listening on http://{addr}");
+
+    axum::serve(listener, router)
+        .await
+        .context("Failed to serve the HTTP API")
+}
+
+async fn serve_grpc(addr: SocketAddr, args: &EngineArgs, webhook: &WebhookArgs) -> Result<()> {
+    let Components { builder, .. } = args.components()?;
+    let engine = builder.build();
+    webhook.spawn(&engine);
+    eprintln!("Listening on grpc://{addr}");
+
+    tonic::transport::Server::builder()
+        .add_service(grpc_server(engine))
+        .serve(addr)
+        .await
+        .context("Failed to serve the gRPC API")
+}
+
+async fn listen(
+    tcp: Option<SocketAddr>,
+    unix: Option<&Path>,
+    format: LineFormat,
+    capacity: NonZeroUsize,
+    args: &EngineArgs,
+    webhook: &WebhookArgs,
+) -> Result<()> {
+    let Components { builder, .. } = args.components()?;
+    let engine = builder.build();
+    webhook.spawn(&engine);
+    let (ingest, _engine) = Ingest::spawn(engine, capacity);
+
+    let mut servers: Vec<BoxFuture<'_, Result<()>>> = Vec::new();
+    if let Some(addr) = tcp {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind to address: {addr}"))?;
+        eprintln!("Listening on tcp://{addr}");
+        servers.push(serve_tcp(listener, ingest.clone(), format).boxed());
+    }
+    if let Some(path) = unix {
+        servers.push(listen_unix(path, ingest.clone(), format)?);
+    }
+
+    future::try_join_all(servers).await?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn listen_unix(
+    path: &Path,
+    ingest: Ingest,
+    format: LineFormat,
+) -> Result<BoxFuture<'static, Result<()>>> {
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind to Unix socket: {}", path.display()))?;
+    eprintln!("Listening on unix://{}", path.display());
+
+    Ok(serve_unix(listener, ingest, format).boxed())
+}
+
+#[cfg(not(unix))]
+fn listen_unix(
+    _path: &Path,
+    _ingest: Ingest,
+    _format: LineFormat,
+) -> Result<BoxFuture<'static, Result<()>>> {
+    bail!("Unix domain sockets are not supported on this platform")
+}
+
+fn verify_audit_log(path: &Path) -> Result<()> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+
+    match verify(BufReader::new(file))? {
+        Verification::Intact { entries } => {
+            println!("The audit log is intact: {entries} entries verified");
+            Ok(())
+        }
+        Verification::Broken { line, reason } => {
+            bail!("The audit log is broken at line {line}: {reason}")
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+    exponential_buckets,
+};
+
+use crate::models::rejection::Rejection;
+use crate::models::transaction::TxRecord;
+
+/// The Prometheus metrics of the engine, see [crate::prelude::PaymentEngineBuilder::metrics].
+///
+/// - `payment_engine_records_total{type, outcome}` counts the records by type and outcome (`applied`, `rejected` or `failed`)
+/// - `payment_engine_rejections_total{type, reason}` counts the rejected records by the reason of the [Rejection]
+/// - `payment_engine_flags_total{policy, cause}` counts the records flagged by a [crate::prelude::TxPolicy]
+/// - `payment_engine_sink_failures_total{sink}` counts the records, that couldn't be written to a sink, i.e. the `journal`, the `audit_log` or the `ledger`
+/// - `payment_engine_record_duration_seconds{type}` is the latency of processing a record
+/// - `payment_engine_repository_duration_seconds{repository, operation}` is the latency of the repository calls of the engine
+/// - `payment_engine_accounts`, `payment_engine_locked_accounts` and `payment_engine_open_disputes` are gauges, that are read from the repositories,
+///   when the metrics are encoded, see [crate::prelude::PaymentEngine::encode_metrics]
+pub struct EngineMetrics {
+    registry: Registry,
+    records: IntCounterVec,
+    rejections: IntCounterVec,
+    flags: IntCounterVec,
+    sink_failures: IntCounterVec,
+    record_duration: HistogramVec,
+    repository_duration: HistogramVec,
+    pub(crate) accounts: IntGauge,
+    pub(crate) locked_accounts: IntGauge,
+    pub(crate) open_disputes: IntGauge,
+}
+
+impl EngineMetrics {
+    pub fn new() -> Result<Self> {
+        // The in-memory repositories answer within microseconds, thus the default buckets, starting at 5ms, would be too coarse
+        let buckets = exponential_buckets(1e-6, 4.0, 10).context("Failed to create the buckets")?;
+
+        let records = IntCounterVec::new(
+            Opts::new(
+                "payment_engine_records_total",
+                "Processed records by type and outcome",
+            ),
+            &["type", "outcome"],
+        )?;
+        let rejections = IntCounterVec::new(
+            Opts::new(
+                "payment_engine_rejections_total",
+                "Rejected records by type and reason",
+            ),
+            &["type", "reason"],
+        )?;
+        let flags = IntCounterVec::new(
+            Opts::new(
+                "payment_engine_flags_total",
+                "Records flagged by a policy, by policy and cause",
+            ),
+            &["policy", "cause"],
+        )?;
+        let sink_failures = IntCounterVec::new(
+            Opts::new(
+                "payment_engine_sink_failures_total",
+                "Records, that couldn't be written to a sink, by sink",
+            ),
+            &["sink"],
+        )?;
+        let record_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "payment_engine_record_duration_seconds",
+                "Latency of processing a record",
+            )
+            .buckets(buckets.clone()),
+            &["type"],
+        )?;
+        let repository_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "payment_engine_repository_duration_seconds",
+                "Latency of the repository calls of the engine",
+            )
+            .buckets(buckets),
+            &["repository", "operation"],
+        )?;
+        let accounts = IntGauge::new("payment_engine_accounts", "Number of accounts")?;
+        let locked_accounts = IntGauge::new(
+            "payment_engine_locked_accounts",
+            "Number of locked accounts",
+        )?;
+        let open_disputes = IntGauge::new(
+            "payment_engine_open_disputes",
+            "Number of disputed transactions, that are neither resolved nor charged back",
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(records.clone()))?;
+        registry.register(Box::new(rejections.clone()))?;
+        registry.register(Box::new(flags.clone()))?;
+        registry.register(Box::new(sink_failures.clone()))?;
+        registry.register(Box::new(record_duration.clone()))?;
+        registry.register(Box::new(repository_duration.clone()))?;
+        registry.register(Box::new(accounts.clone()))?;
+        registry.register(Box::new(locked_accounts.clone()))?;
+        registry.register(Box::new(open_disputes.clone()))?;
+
+        Ok(Self {
+            registry,
+            records,
+            rejections,
+            flags,
+            sink_failures,
+            record_duration,
+            repository_duration,
+            accounts,
+            locked_accounts,
+            open_disputes,
+        })
+    }
+
+    /// The registry of the metrics, e.g. for exposing them together with the metrics of the host application
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub(crate) fn observe_record(&self, record: &TxRecord, res: &Result<()>, elapsed: Duration) {
+        let tx_type = record.type_name();
+        let outcome = match res {
+            Ok(()) => "applied",
+            Err(err) => match Rejection::of(err) {
+                Some(rejection) => {
+                    self.rejections
+                        .with_label_values(&[tx_type, rejection.reason()])
+                        .inc();
+                    "rejected"
+                }
+                None => "failed",
+            },
+        };
+
+        self.records.with_label_values(&[tx_type, outcome]).inc();
+        self.record_duration
+            .with_label_values(&[tx_type])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub(crate) fn observe_flag(&self, policy: &'static str, cause: &str) {
+        self.flags.with_label_values(&[policy, cause]).inc();
+    }
+
+    pub(crate) fn observe_sink_failure(&self, sink: &'static str) {
+        self.sink_failures.with_label_values(&[sink]).inc();
+    }
+
+    pub(crate) fn observe_repository(
+        &self,
+        repository: &'static str,
+        operation: &'static str,
+        elapsed: Duration,
+    ) {
+        self.repository_duration
+            .with_label_values(&[repository, operation])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Encodes the current values in the Prometheus text format
+    pub(crate) fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .context("Failed to encode the metrics")?;
+
+        String::from_utf8(buffer).context("Failed to encode the metrics as UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use claims::assert_ok;
+
+    use super::*;
+    use crate::models::client::ClientId;
+    use crate::models::transaction::{Dispute, TransactionId};
+
+    #[test]
+    fn counts_the_outcome_and_reason() {
+        let metrics = assert_ok!(EngineMetrics::new());
+        let record = TxRecord::Dispute(Dispute {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+        });
+        let rejected = Err(anyhow!(Rejection::UnknownAccount {
+            client: ClientId::new(1)
+        }));
+
+        metrics.observe_record(&record, &Ok(()), Duration::from_micros(3));
+        metrics.observe_record(&record, &rejected, Duration::from_micros(3));
+        metrics.observe_record(&record, &Err(anyhow!("boom")), Duration::from_micros(3));
+
+        let text = assert_ok!(metrics.encode());
+        for line in [
+            r#"payment_engine_records_total{outcome="applied",type="dispute"} 1"#,
+            r#"payment_engine_records_total{outcome="rejected",type="dispute"} 1"#,
+            r#"payment_engine_records_total{outcome="failed",type="dispute"} 1"#,
+            r#"payment_engine_rejections_total{reason="unknown_account",type="dispute"} 1"#,
+            r#"payment_engine_record_duration_seconds_count{type="dispute"} 3"#,
+        ] {
+            assert!(text.contains(line), "Missing {line} in:\n{text}");
+        }
+    }
+}
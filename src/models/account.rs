@@ -1,13 +1,14 @@
 use anyhow::{Result, bail};
 use rust_decimal::Decimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::client::ClientId;
+use super::rejection::Rejection;
 
 /// This type represent the client asset account.
 ///
 /// It also hold the critical calculations, thus is intensivele tested with unit test, that can be found in the [tests] submodule
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Account {
     #[serde(rename = "client")]
     pub client_id: ClientId,
@@ -32,7 +33,7 @@ impl Account {
 
     pub fn deposit(&mut self, amount: Decimal) -> Result<()> {
         if amount < Decimal::ZERO {
-            bail!("Failed to withdraw a negative amount of {amount:}.")
+            bail!(Rejection::NegativeAmount { amount })
         }
 
         self.available = self.available.saturating_add(amount);
@@ -43,14 +44,14 @@ impl Account {
 
     pub fn try_withdrawal(&mut self, amount: Decimal) -> Result<()> {
         if amount < Decimal::ZERO {
-            bail!("Failed to withdraw a negative amount of {amount:}.")
+            bail!(Rejection::NegativeAmount { amount })
         }
 
         if amount > self.available {
-            bail!(
-                "Failed to withdraw the amount of {amount:}. No sufficient funds available: {:}",
-                self.available
-            )
+            bail!(Rejection::InsufficientFunds {
+                amount,
+                available: self.available
+            })
         }
 
         self.available = self.available.saturating_sub(amount);
@@ -69,13 +70,14 @@ impl Account {
             // Raised a dispute for a deposit
             Direction::Decrease(amount) => {
                 if amount < Decimal::ZERO {
-                    bail!("Failed to dispute a negative amount of {amount:}.")
+                    bail!(Rejection::NegativeAmount { amount })
                 }
 
                 if amount > self.available {
-                    bail!(
-                        "Failed to dispute an amount:{amount:} that is greater than the available fund."
-                    )
+                    bail!(Rejection::InsufficientFunds {
+                        amount,
+                        available: self.available
+                    })
                 }
 
                 self.available = self.available.saturating_sub(amount);
@@ -96,7 +98,7 @@ impl Account {
             // Raised a dispute for a deposit
             Direction::Decrease(amount) => {
                 if amount < Decimal::ZERO {
-                    bail!("Failed to dispute a negative amount of {amount:}.")
+                    bail!(Rejection::NegativeAmount { amount })
                 }
 
                 self.available = self.available.saturating_add(amount);
@@ -116,7 +118,7 @@ impl Account {
             // Raised a dispute for a deposit
             Direction::Decrease(amount) => {
                 if amount < Decimal::ZERO {
-                    bail!("Failed to dispute a negative amount of {amount:}.")
+                    bail!(Rejection::NegativeAmount { amount })
                 }
 
                 self.available = self.available.saturating_sub(amount);
@@ -0,0 +1,282 @@
+use anyhow::{Context, Result, bail};
+use rust_decimal::Decimal;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use super::client::ClientId;
+use super::transaction::{Transaction, TransactionType, TxRecord};
+
+/// The sub-ledgers of the double-entry bookkeeping.
+///
+/// The client sub-ledgers are liabilities of the engine towards the client, thus they are credit-normal.
+/// Money enters and leaves the engine through the fiat clearing. The chargeback loss carries the funds, that are provisionally credited to a client disputing a withdrawal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SubLedger {
+    ClientAvailable(ClientId),
+    ClientHeld(ClientId),
+    FiatClearing,
+    ChargebackLoss,
+}
+
+impl SubLedger {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubLedger::ClientAvailable(_) => "client_available",
+            SubLedger::ClientHeld(_) => "client_held",
+            SubLedger::FiatClearing => "fiat_clearing",
+            SubLedger::ChargebackLoss => "chargeback_loss",
+        }
+    }
+
+    pub fn client_id(&self) -> Option<ClientId> {
+        match self {
+            SubLedger::ClientAvailable(client_id) | SubLedger::ClientHeld(client_id) => {
+                Some(*client_id)
+            }
+            SubLedger::FiatClearing | SubLedger::ChargebackLoss => None,
+        }
+    }
+}
+
+/// A single side of a journal entry. Debits are positive, credits are negative amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Posting {
+    pub sub_ledger: SubLedger,
+    pub amount: Decimal,
+}
+
+impl Posting {
+    pub fn debit(sub_ledger: SubLedger, amount: Decimal) -> Self {
+        Self { sub_ledger, amount }
+    }
+
+    pub fn credit(sub_ledger: SubLedger, amount: Decimal) -> Self {
+        Self {
+            sub_ledger,
+            amount: -amount,
+        }
+    }
+}
+
+/// The postings of an applied [TxRecord]. The postings of every entry sum up to zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub record: TxRecord,
+    pub postings: Vec<Posting>,
+}
+
+impl JournalEntry {
+    pub fn is_balanced(&self) -> bool {
+        is_balanced(&self.postings)
+    }
+}
+
+pub fn is_balanced(postings: &[Posting]) -> bool {
+    postings
+        .iter()
+        .map(|posting| posting.amount)
+        .sum::<Decimal>()
+        .is_zero()
+}
+
+/// Derives the postings of an applied record. Disputes, resolves and chargebacks require the referenced transaction.
+///
+/// | record                | debit             | credit           |
+/// |-----------------------|-------------------|------------------|
+/// | deposit               | fiat clearing     | client available |
+/// | withdrawal            | client available  | fiat clearing    |
+/// | dispute of deposit    | client available  | client held      |
+/// | dispute of withdrawal | chargeback loss   | client held      |
+/// | resolve               | client held       | client available |
+/// | chargeback of deposit | client available  | fiat clearing    |
+///
+/// The chargeback of a withdrawal doesn't move any funds, because they have already been credited by its dispute.
+pub fn postings_for(record: &TxRecord, referenced: Option<&Transaction>) -> Result<Vec<Posting>> {
+    let client_id = record.client_id();
+    let available = SubLedger::ClientAvailable(client_id);
+    let held = SubLedger::ClientHeld(client_id);
+
+    let postings = match record {
+        TxRecord::Deposit(deposit) => transfer(
+            SubLedger::FiatClearing,
+            available,
+            deposit.amount.into_inner(),
+        ),
+        TxRecord::Withdrawal(withdrawal) => transfer(
+            available,
+            SubLedger::FiatClearing,
+            withdrawal.amount.into_inner(),
+        ),
+        TxRecord::Dispute(_) | TxRecord::Resolve(_) | TxRecord::Chargeback(_) => {
+            let tx = referenced.with_context(|| {
+                format!(
+                    "Failed to derive the postings, because the referenced tx: {:?} is missing",
+                    record.tx_id()
+                )
+            })?;
+            let amount = tx.amount.into_inner();
+
+            match (record, tx.tx_type) {
+                (TxRecord::Dispute(_), TransactionType::Deposit) => {
+                    transfer(available, held, amount)
+                }
+                (TxRecord::Dispute(_), TransactionType::Withdrawal) => {
+                    transfer(SubLedger::ChargebackLoss, held, amount)
+                }
+                (TxRecord::Resolve(_), _) => transfer(held, available, amount),
+                (TxRecord::Chargeback(_), TransactionType::Deposit) => {
+                    transfer(available, SubLedger::FiatClearing, amount)
+                }
+                (TxRecord::Chargeback(_), TransactionType::Withdrawal) => Vec::new(),
+                _ => unreachable!("Deposits and withdrawals don't reference a transaction"),
+            }
+        }
+    };
+
+    if !is_balanced(&postings) {
+        bail!("Failed to derive balanced postings for the record: {record:?}");
+    }
+
+    Ok(postings)
+}
+
+fn transfer(debit: SubLedger, credit: SubLedger, amount: Decimal) -> Vec<Posting> {
+    vec![
+        Posting::debit(debit, amount),
+        Posting::credit(credit, amount),
+    ]
+}
+
+/// The balances of all sub-ledgers. For a sound journal, they sum up to zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrialBalance {
+    pub lines: Vec<TrialBalanceLine>,
+}
+
+impl TrialBalance {
+    pub fn sum(&self) -> Decimal {
+        self.lines.iter().map(|line| line.balance).sum()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.sum().is_zero()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrialBalanceLine {
+    pub sub_ledger: SubLedger,
+    pub balance: Decimal,
+}
+
+/// Serializes the sub-ledger as name and optional client, so that a line fits into CSV columns.
+impl Serialize for TrialBalanceLine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut line = serializer.serialize_struct("TrialBalanceLine", 3)?;
+        line.serialize_field("ledger", self.sub_ledger.name())?;
+        line.serialize_field("client", &self.sub_ledger.client_id())?;
+        line.serialize_field("balance", &self.balance)?;
+        line.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use rust_decimal::dec;
+
+    use super::*;
+    use crate::models::NonNegativeDecimal;
+    use crate::models::transaction::{
+        Chargeback, Deposit, Dispute, Resolve, TransactionId, TransactionStatus,
+    };
+
+    const CLIENT: ClientId = ClientId::new(1);
+
+    fn tx(tx_type: TransactionType) -> Transaction {
+        Transaction {
+            tx_type,
+            client_id: CLIENT,
+            id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+            status: TransactionStatus::Processed,
+        }
+    }
+
+    #[test]
+    fn deposit_credits_the_client() {
+        let record = TxRecord::from(Deposit {
+            client_id: CLIENT,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        });
+
+        let postings = assert_ok!(postings_for(&record, None));
+
+        assert_eq!(
+            postings,
+            [
+                Posting::debit(SubLedger::FiatClearing, dec!(10)),
+                Posting::credit(SubLedger::ClientAvailable(CLIENT), dec!(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn dispute_of_withdrawal_is_funded_by_the_chargeback_loss() {
+        let record = TxRecord::from(Dispute {
+            client_id: CLIENT,
+            tx_id: TransactionId::new(1),
+        });
+
+        let postings = assert_ok!(postings_for(
+            &record,
+            Some(&tx(TransactionType::Withdrawal))
+        ));
+
+        assert_eq!(
+            postings,
+            [
+                Posting::debit(SubLedger::ChargebackLoss, dec!(10)),
+                Posting::credit(SubLedger::ClientHeld(CLIENT), dec!(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn postings_are_always_balanced() {
+        let tx_id = TransactionId::new(1);
+        let records = [
+            TxRecord::from(Dispute {
+                client_id: CLIENT,
+                tx_id,
+            }),
+            TxRecord::from(Resolve {
+                client_id: CLIENT,
+                tx_id,
+            }),
+            TxRecord::from(Chargeback {
+                client_id: CLIENT,
+                tx_id,
+            }),
+        ];
+
+        for record in records {
+            for tx_type in [TransactionType::Deposit, TransactionType::Withdrawal] {
+                let postings = assert_ok!(postings_for(&record, Some(&tx(tx_type))));
+                assert!(is_balanced(&postings), "Unbalanced: {record:?} {tx_type:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn referenced_tx_is_required() {
+        let record = TxRecord::from(Resolve {
+            client_id: CLIENT,
+            tx_id: TransactionId::new(1),
+        });
+
+        assert_err!(postings_for(&record, None));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use super::account::Account;
+use super::client::ClientId;
+use super::transaction::TxRecord;
+
+/// A [LedgerEntry] records a single [TxRecord] processed by the engine, regardless of whether it has been applied or rejected.
+///
+/// Besides the outcome, it captures the account of the client before and after the record. An account, that doesn't exist (yet), is `None`.
+/// Entries are never changed after they have been appended, thus the ledger is the audit trail of the engine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LedgerEntry {
+    /// Sequence number, starting with 1 and increasing without gaps
+    pub seq: u64,
+    pub record: TxRecord,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub before: Option<Account>,
+    pub after: Option<Account>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum Outcome {
+    Applied,
+    Rejected { reason: String },
+}
+
+impl Outcome {
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Outcome::Applied)
+    }
+}
+
+impl<T> From<&Result<T>> for Outcome {
+    fn from(res: &Result<T>) -> Self {
+        match res {
+            Ok(_) => Outcome::Applied,
+            // The alternate format renders the whole context chain on a single line
+            Err(err) => Outcome::Rejected {
+                reason: format!("{err:#}"),
+            },
+        }
+    }
+}
+
+/// Replays the entries in sequence order and derives the account balances purely from the ledger, ordered by client.
+///
+/// Every entry has to continue from the account left behind by the previous entry of the same client.
+/// Otherwise, the ledger is incomplete or has been altered, which is reported as an error.
+pub fn reconstruct_balances<I>(entries: I) -> Result<Vec<Account>>
+where
+    I: IntoIterator<Item = LedgerEntry>,
+{
+    let mut accounts: BTreeMap<ClientId, Account> = BTreeMap::new();
+
+    for entry in entries {
+        let client_id = entry.record.client_id();
+        let current = accounts.get(&client_id).copied();
+
+        if current != entry.before {
+            bail!(
+                "Failed to reconstruct the balances, because the entry with seq: {} doesn't continue from the previous entry of client: {client_id:?}",
+                entry.seq
+            );
+        }
+
+        if let Some(after) = entry.after {
+            accounts.insert(client_id, after);
+        }
+    }
+
+    Ok(accounts.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use rust_decimal::{Decimal, dec};
+
+    use super::*;
+    use crate::models::NonNegativeDecimal;
+    use crate::models::transaction::{Deposit, TransactionId, Withdrawal};
+
+    fn deposit(tx_id: u32, amount: i32) -> TxRecord {
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(tx_id),
+            amount: NonNegativeDecimal::try_from(amount).unwrap(),
+        })
+    }
+
+    fn account(available: Decimal) -> Account {
+        Account {
+            available,
+            total: available,
+            ..Account::new(ClientId::new(1))
+        }
+    }
+
+    #[test]
+    fn balances_are_taken_from_the_last_entry_of_each_client() {
+        let entries = [
+            LedgerEntry {
+                seq: 1,
+                record: deposit(1, 10),
+                outcome: Outcome::Applied,
+                before: None,
+                after: Some(account(dec!(10))),
+            },
+            LedgerEntry {
+                seq: 2,
+                record: TxRecord::from(Withdrawal {
+                    client_id: ClientId::new(1),
+                    tx_id: TransactionId::new(2),
+                    amount: NonNegativeDecimal::try_from(20).unwrap(),
+                }),
+                outcome: Outcome::Rejected {
+                    reason: "No sufficient funds".to_owned(),
+                },
+                before: Some(account(dec!(10))),
+                after: Some(account(dec!(10))),
+            },
+            LedgerEntry {
+                seq: 3,
+                record: deposit(3, 5),
+                outcome: Outcome::Applied,
+                before: Some(account(dec!(10))),
+                after: Some(account(dec!(15))),
+            },
+        ];
+
+        let balances = assert_ok!(reconstruct_balances(entries));
+
+        assert_eq!(balances, [account(dec!(15))]);
+    }
+
+    #[test]
+    fn detects_a_gap_in_the_ledger() {
+        let entries = [
+            LedgerEntry {
+                seq: 1,
+                record: deposit(1, 10),
+                outcome: Outcome::Applied,
+                before: None,
+                after: Some(account(dec!(10))),
+            },
+            LedgerEntry {
+                seq: 3,
+                record: deposit(3, 5),
+                outcome: Outcome::Applied,
+                before: Some(account(dec!(20))),
+                after: Some(account(dec!(25))),
+            },
+        ];
+
+        let res = reconstruct_balances(entries);
+
+        assert_err!(res, "Expected the missing entry to be detected");
+    }
+}
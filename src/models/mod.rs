@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 
 pub mod account;
 pub mod client;
+pub mod journal;
+pub mod ledger;
+pub mod rejection;
 pub mod transaction;
 
 /// This type represents a non negative decimal for being used at the outer boundaries of the domain, enforcing this constraint.
@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::client::ClientId;
+use super::transaction::{TransactionId, TransactionStatus};
+
+/// The business reason, why the engine rejected a record.
+///
+/// Rejections are raised as errors, thus they travel through the engine like any other error and may be wrapped in additional context.
+/// A caller, that needs to react on the reason, e.g. the HTTP API, gets it back by [Rejection::of]. Every other error is an unexpected failure, e.g. of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    /// The tx id has already been used, see [crate::prelude::IdempotencyPolicy]
+    Duplicate {
+        tx: TransactionId,
+        status: TransactionStatus,
+    },
+    /// The tx id has already been used by a transaction, that is not retained anymore
+    ExpiredDuplicate {
+        tx: TransactionId,
+    },
+    NegativeAmount {
+        amount: Decimal,
+    },
+    PrecisionExceeded {
+        amount: Decimal,
+        precision: u32,
+    },
+    MaxAmountExceeded {
+        amount: Decimal,
+        max_amount: Decimal,
+    },
+    UnknownAccount {
+        client: ClientId,
+    },
+    AccountLocked {
+        client: ClientId,
+    },
+    InsufficientFunds {
+        amount: Decimal,
+        available: Decimal,
+    },
+    /// The referenced transaction either doesn't exist or doesn't have the expected status
+    InvalidReference {
+        tx: TransactionId,
+        expected: TransactionStatus,
+    },
+    /// Only deposits can be disputed, see [crate::prelude::DisputePolicy]
+    NotDisputable {
+        tx: TransactionId,
+    },
+    DisputeWindowExpired {
+        tx: TransactionId,
+    },
+    /// The record exceeds a limit of the client, see [crate::prelude::LimitsConfig]
+    LimitExceeded {
+        client: ClientId,
+        limit: Limit,
+    },
+    /// A custom policy rejected the record, see [crate::prelude::TxPolicy]
+    PolicyRejected {
+        policy: &'static str,
+        // The tag of the rejection is already called reason
+        cause: Cow<'static, str>,
+    },
+}
+
+/// The kind of the exceeded limit, see [crate::prelude::TxLimits]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Limit {
+    MaxAmount,
+    DailyAmount,
+    Velocity,
+}
+
+impl Rejection {
+    /// Returns the rejection of the error, also if it has been wrapped in context.
+    pub fn of(err: &anyhow::Error) -> Option<&Rejection> {
+        err.downcast_ref()
+    }
+
+    /// The name of the reason, as serialized in the `reason` tag, e.g. for labeling metrics.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Rejection::Duplicate { .. } => "duplicate",
+            Rejection::ExpiredDuplicate { .. } => "expired_duplicate",
+            Rejection::NegativeAmount { .. } => "negative_amount",
+            Rejection::PrecisionExceeded { .. } => "precision_exceeded",
+            Rejection::MaxAmountExceeded { .. } => "max_amount_exceeded",
+            Rejection::UnknownAccount { .. } => "unknown_account",
+            Rejection::AccountLocked { .. } => "account_locked",
+            Rejection::InsufficientFunds { .. } => "insufficient_funds",
+            Rejection::InvalidReference { .. } => "invalid_reference",
+            Rejection::NotDisputable { .. } => "not_disputable",
+            Rejection::DisputeWindowExpired { .. } => "dispute_window_expired",
+            Rejection::LimitExceeded { .. } => "limit_exceeded",
+            Rejection::PolicyRejected { .. } => "policy_rejected",
+        }
+    }
+}
+
+impl StdError for Rejection {}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Duplicate { tx, status } => write!(
+                f,
+                "Rejected tx: {tx:?} as a duplicate, because the tx id has already been used by a transaction with status: {status:?}"
+            ),
+            Rejection::ExpiredDuplicate { tx } => write!(
+                f,
+                "Rejected tx: {tx:?} as a duplicate, because the tx id has already been used by a transaction outside of the retention window"
+            ),
+            Rejection::NegativeAmount { amount } => {
+                write!(f, "Failed to process a negative amount of {amount}")
+            }
+            Rejection::PrecisionExceeded { amount, precision } => write!(
+                f,
+                "Failed to process the amount: {amount}, because it exceeds the precision of {precision} decimal places"
+            ),
+            Rejection::MaxAmountExceeded { amount, max_amount } => write!(
+                f,
+                "Failed to process the amount: {amount}, because it exceeds the maximum amount of {max_amount}"
+            ),
+            Rejection::UnknownAccount { client } => {
+                write!(f, "Failed to find an account for client_id: {client:?}")
+            }
+            Rejection::AccountLocked { client } => write!(
+                f,
+                "Failed to process tx, because the account of client_id: {client:?} is locked"
+            ),
+            Rejection::InsufficientFunds { amount, available } => write!(
+                f,
+                "Failed to take the amount of {amount}. No sufficient funds available: {available}"
+            ),
+            Rejection::InvalidReference { tx, expected } => write!(
+                f,
+                "Failed to process tx, because the referenced transaction: {tx:?} either does not exist or does not have the status: {expected:?}"
+            ),
+            Rejection::NotDisputable { tx } => write!(
+                f,
+                "Failed to dispute tx: {tx:?}, because only deposits can be disputed"
+            ),
+            Rejection::DisputeWindowExpired { tx } => write!(
+                f,
+                "Failed to process tx: {tx:?}, because the dispute window expired for the referenced transaction"
+            ),
+            Rejection::LimitExceeded { client, limit } => write!(
+                f,
+                "Failed to process tx, because it exceeds the {limit:?} limit of client_id: {client:?}"
+            ),
+            Rejection::PolicyRejected { policy, cause } => write!(
+                f,
+                "Failed to process tx, because the policy: {policy} rejected it for: {cause}"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, Result, bail};
+    use claims::assert_some_eq;
+
+    use super::*;
+
+    #[test]
+    fn rejection_survives_context() {
+        let rejection = Rejection::AccountLocked {
+            client: ClientId::new(1),
+        };
+        let res: Result<()> = (|| bail!(rejection.clone()))().context("outer context");
+
+        let err = res.unwrap_err();
+
+        assert_some_eq!(Rejection::of(&err), &rejection);
+    }
+
+    #[test]
+    fn reason_is_serialized_as_tag() {
+        let rejection = Rejection::UnknownAccount {
+            client: ClientId::new(7),
+        };
+
+        let json = serde_json::to_string(&rejection).unwrap();
+
+        assert_eq!(json, r#"{"reason":"unknown_account","client":7}"#);
+        assert_eq!(rejection.reason(), "unknown_account");
+    }
+}
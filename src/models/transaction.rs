@@ -5,7 +5,10 @@ use crate::models::client::ClientId;
 
 /// This is the main type used in the stream processed by the engine.
 /// Its enum variants are specialized to their use cases.
-#[derive(Debug)]
+///
+/// It (de)serializes with the same field names as the CSV input, e.g. `{"type":"deposit","client":1,"tx":1,"amount":"1.0"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum TxRecord {
     Deposit(Deposit),
     Withdrawal(Withdrawal),
@@ -14,38 +17,81 @@ pub enum TxRecord {
     Chargeback(Chargeback),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Deposit {
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub tx_id: TransactionId,
     pub amount: NonNegativeDecimal,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Withdrawal {
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub tx_id: TransactionId,
     pub amount: NonNegativeDecimal,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Dispute {
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub tx_id: TransactionId,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Resolve {
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub tx_id: TransactionId,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Chargeback {
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub tx_id: TransactionId,
 }
 
+impl TxRecord {
+    pub fn client_id(&self) -> ClientId {
+        match self {
+            TxRecord::Deposit(deposit) => deposit.client_id,
+            TxRecord::Withdrawal(withdrawal) => withdrawal.client_id,
+            TxRecord::Dispute(dispute) => dispute.client_id,
+            TxRecord::Resolve(resolve) => resolve.client_id,
+            TxRecord::Chargeback(cb) => cb.client_id,
+        }
+    }
+
+    /// The name of the type, as in the `type` column of the CSV input
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TxRecord::Deposit(_) => "deposit",
+            TxRecord::Withdrawal(_) => "withdrawal",
+            TxRecord::Dispute(_) => "dispute",
+            TxRecord::Resolve(_) => "resolve",
+            TxRecord::Chargeback(_) => "chargeback",
+        }
+    }
+
+    pub fn tx_id(&self) -> TransactionId {
+        match self {
+            TxRecord::Deposit(deposit) => deposit.tx_id,
+            TxRecord::Withdrawal(withdrawal) => withdrawal.tx_id,
+            TxRecord::Dispute(dispute) => dispute.tx_id,
+            TxRecord::Resolve(resolve) => resolve.tx_id,
+            TxRecord::Chargeback(cb) => cb.tx_id,
+        }
+    }
+}
+
 impl From<Deposit> for TxRecord {
     fn from(deposit: Deposit) -> Self {
         Self::Deposit(deposit)
@@ -76,10 +122,14 @@ impl From<Chargeback> for TxRecord {
     }
 }
 
-#[derive(Clone, Copy)]
+/// The field names are aligned with the CSV input, extended by the final status of the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct Transaction {
+    #[serde(rename = "type")]
     pub tx_type: TransactionType,
+    #[serde(rename = "client")]
     pub client_id: ClientId,
+    #[serde(rename = "tx")]
     pub id: TransactionId,
     pub amount: NonNegativeDecimal,
     pub status: TransactionStatus,
@@ -107,7 +157,8 @@ impl Transaction {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TransactionStatus {
     Processed,
     Failed,
@@ -116,17 +167,22 @@ pub enum TransactionStatus {
     Chargedback,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TransactionId(u32);
 
 impl TransactionId {
     pub fn new(id: u32) -> Self {
         Self(id)
     }
+
+    pub fn into_inner(self) -> u32 {
+        self.0
+    }
 }
 
 /// This type is used in the TransactionRepository and only offers the necessary variants for persisting Deposits and Withdrawals.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TransactionType {
     Deposit,
     Withdrawal,
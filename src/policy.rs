@@ -0,0 +1,41 @@
+use std::borrow::Cow;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::models::account::Account;
+use crate::models::transaction::TxRecord;
+use crate::repository::transaction::TransactionRepository;
+
+/// A [TxPolicy] is a custom rule, e.g. a fraud check, that is evaluated for every record before the engine applies it.
+///
+/// The policies are configured at the construction of the engine, see [crate::prelude::PaymentEngineBuilder::policy].
+/// They are evaluated in the order they have been added. The first rejecting policy rejects the record with [crate::models::rejection::Rejection::PolicyRejected],
+/// the remaining ones are skipped. A flagged record is still applied, but the flag is logged and counted in the metrics.
+///
+/// An error of a policy, e.g. of a remote service, fails the record like an error of a repository.
+#[async_trait]
+pub trait TxPolicy: Send + Sync {
+    /// The name of the policy, as reported in a rejection or flag
+    fn name(&self) -> &'static str;
+
+    async fn evaluate(&self, record: &TxRecord, context: &PolicyContext<'_>) -> Result<Decision>;
+}
+
+/// The cause of a rejection or flag is either static, e.g. `"blocked_country".into()`, or built from the record, e.g. `format!("{count} withdrawals within an hour").into()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    /// Rejects the record for the given cause
+    Reject(Cow<'static, str>),
+    /// Applies the record, but marks it for a review. The cause is a label of the flags metric, thus it should only take a few distinct values.
+    Flag(Cow<'static, str>),
+}
+
+/// Read access to the state of the engine, as it is before the record is applied.
+pub struct PolicyContext<'a> {
+    /// The account of the client, if there is one
+    pub account: Option<Account>,
+    /// The stored transactions, e.g. for reading the history of the client by [TransactionRepository::history] or [TransactionRepository::query]
+    pub transactions: &'a dyn TransactionRepository,
+}
@@ -1,4 +1,36 @@
-pub use crate::csv::{CsvDecoder, CsvEncoder};
-pub use crate::engine::PaymentEngine;
-pub use crate::repository::account::{AccountRepository, InMemoryAccountRepository};
-pub use crate::repository::transaction::{InMemoryTxRepository, TransactionRepository};
+pub use crate::audit::{AuditLog, AuditSink, Verification, verify};
+pub use crate::bus::{
+    InMemoryBroker, InMemoryConsumer, InMemoryProducer, Message, OutcomePublisher,
+    PartitionConsumer, Producer, process_partition,
+};
+pub use crate::config::{
+    DisputePolicy, EngineConfig, FailureMode, IdempotencyPolicy, LimitsConfig, LockedAccountPolicy,
+    TxLimits, Velocity,
+};
+pub use crate::csv::{CsvDecoder, CsvEncoder, DecodeStats};
+pub use crate::diff::{BalanceDiff, DiffSummary, diff, diff_engines};
+pub use crate::engine::{PaymentEngine, PaymentEngineBuilder, RecordOutcome};
+pub use crate::events::{AccountEvent, Webhook};
+pub use crate::grpc::{GrpcService, grpc_server, proto};
+pub use crate::http::http_router;
+pub use crate::json::{JsonEncoder, JsonLinesEncoder};
+pub use crate::metrics::EngineMetrics;
+pub use crate::policy::{Decision, PolicyContext, TxPolicy};
+pub use crate::reconcile::{
+    AccountField, Discrepancy, DiscrepancyKind, FieldValue, Reconciliation, reconcile,
+};
+pub use crate::repository::Page;
+pub use crate::repository::account::{
+    AccountQuery, AccountRepository, AccountTotals, BalanceOrder, InMemoryAccountRepository,
+};
+pub use crate::repository::journal::{InMemoryJournal, JournalRepository};
+pub use crate::repository::ledger::{InMemoryLedger, LedgerRepository};
+pub use crate::repository::seen_index::{BitmapSeenIndex, RoaringSeenIndex, SeenTxIndex};
+pub use crate::repository::transaction::{
+    InMemoryTxRepository, TransactionRepository, TxQuery, TxRetention,
+};
+#[cfg(unix)]
+pub use crate::socket::serve_unix;
+pub use crate::socket::{Ingest, LineFormat, serve_connection, serve_tcp};
+pub use crate::summary::{Abort, RecordCounts, RunStats, RunSummary, TypeSummary};
+pub use crate::telemetry::{LogFormat, Telemetry};
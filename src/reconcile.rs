@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::iter;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+
+/// The outcome of comparing expected balances with the actual ones, see [reconcile].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Ordered by client, and by field within a client
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl Reconciliation {
+    pub fn is_reconciled(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// A single finding of a reconciliation. It is flat, so that it fits into CSV columns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discrepancy {
+    pub client: ClientId,
+    pub kind: DiscrepancyKind,
+    pub field: Option<AccountField>,
+    pub expected: Option<FieldValue>,
+    pub actual: Option<FieldValue>,
+    /// The actual minus the expected amount
+    pub delta: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// The client is expected, but has no actual account
+    Missing,
+    /// The client has an actual account, but isn't expected
+    Unexpected,
+    /// A field of the account differs
+    Mismatch,
+    /// The client is expected more than once. Reported for every additional row, only the first one is compared.
+    DuplicateExpected,
+    /// The client has more than one actual account. Reported for every additional one, only the first one is compared.
+    DuplicateActual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountField {
+    Available,
+    Held,
+    Total,
+    Locked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Amount(Decimal),
+    Flag(bool),
+}
+
+/// Compares the expected with the actual accounts per client. Amounts are compared by value, i.e. `1.0` equals `1`.
+///
+/// A client appearing more than once on a side is reported as duplicate, rather than letting one of the rows win silently.
+pub fn reconcile<E, A>(expected: E, actual: A) -> Reconciliation
+where
+    E: IntoIterator<Item = Account>,
+    A: IntoIterator<Item = Account>,
+{
+    let mut pairs: BTreeMap<ClientId, (Rows, Rows)> = BTreeMap::new();
+    for acc in expected {
+        pairs.entry(acc.client_id).or_default().0.push(acc);
+    }
+    for acc in actual {
+        pairs.entry(acc.client_id).or_default().1.push(acc);
+    }
+
+    let discrepancies = pairs
+        .into_iter()
+        .flat_map(|(client, (expected, actual))| {
+            let duplicates = iter::repeat_n(
+                Discrepancy::of_client(client, DiscrepancyKind::DuplicateExpected),
+                expected.duplicates,
+            )
+            .chain(iter::repeat_n(
+                Discrepancy::of_client(client, DiscrepancyKind::DuplicateActual),
+                actual.duplicates,
+            ));
+            let differences = match (expected.first, actual.first) {
+                (Some(expected), Some(actual)) => compare(expected, actual),
+                (Some(_), None) => vec![Discrepancy::of_client(client, DiscrepancyKind::Missing)],
+                (None, Some(_)) => {
+                    vec![Discrepancy::of_client(client, DiscrepancyKind::Unexpected)]
+                }
+                (None, None) => Vec::new(),
+            };
+
+            duplicates.chain(differences).collect::<Vec<_>>()
+        })
+        .collect();
+
+    Reconciliation { discrepancies }
+}
+
+/// The rows of a client on one side of the reconciliation
+#[derive(Default)]
+struct Rows {
+    first: Option<Account>,
+    duplicates: usize,
+}
+
+impl Rows {
+    fn push(&mut self, acc: Account) {
+        match self.first {
+            Some(_) => self.duplicates += 1,
+            None => self.first = Some(acc),
+        }
+    }
+}
+
+impl Discrepancy {
+    fn of_client(client: ClientId, kind: DiscrepancyKind) -> Self {
+        Self {
+            client,
+            kind,
+            field: None,
+            expected: None,
+            actual: None,
+            delta: None,
+        }
+    }
+}
+
+fn compare(expected: Account, actual: Account) -> Vec<Discrepancy> {
+    let amounts = [
+        (
+            AccountField::Available,
+            expected.available,
+            actual.available,
+        ),
+        (AccountField::Held, expected.held, actual.held),
+        (AccountField::Total, expected.total, actual.total),
+    ];
+
+    let mut discrepancies: Vec<_> = amounts
+        .into_iter()
+        .filter(|(_, expected, actual)| expected != actual)
+        .map(|(field, expected_amount, actual_amount)| Discrepancy {
+            client: expected.client_id,
+            kind: DiscrepancyKind::Mismatch,
+            field: Some(field),
+            expected: Some(FieldValue::Amount(expected_amount)),
+            actual: Some(FieldValue::Amount(actual_amount)),
+            delta: Some(actual_amount.saturating_sub(expected_amount)),
+        })
+        .collect();
+
+    if expected.is_locked != actual.is_locked {
+        discrepancies.push(Discrepancy {
+            client: expected.client_id,
+            kind: DiscrepancyKind::Mismatch,
+            field: Some(AccountField::Locked),
+            expected: Some(FieldValue::Flag(expected.is_locked)),
+            actual: Some(FieldValue::Flag(actual.is_locked)),
+            delta: None,
+        });
+    }
+
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::dec;
+
+    use super::*;
+
+    fn account(client: u16, available: Decimal) -> Account {
+        Account {
+            available,
+            total: available,
+            ..Account::new(ClientId::new(client))
+        }
+    }
+
+    #[test]
+    fn equal_accounts_are_reconciled() {
+        let accounts = [account(1, dec!(1.5)), account(2, dec!(0))];
+
+        let reconciliation = reconcile(accounts, [account(2, dec!(0.00)), account(1, dec!(1.50))]);
+
+        assert!(reconciliation.is_reconciled(), "unexpected discrepancies");
+    }
+
+    #[test]
+    fn reports_missing_and_unexpected_clients() {
+        let reconciliation = reconcile([account(1, dec!(1))], [account(2, dec!(1))]);
+
+        let kinds: Vec<_> = reconciliation
+            .discrepancies
+            .iter()
+            .map(|discrepancy| (discrepancy.client, discrepancy.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (ClientId::new(1), DiscrepancyKind::Missing),
+                (ClientId::new(2), DiscrepancyKind::Unexpected)
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicated_clients_of_both_sides() {
+        let reconciliation = reconcile(
+            [
+                account(1, dec!(1)),
+                account(1, dec!(2)),
+                account(2, dec!(5)),
+            ],
+            [
+                account(1, dec!(1)),
+                account(2, dec!(5)),
+                account(2, dec!(5)),
+            ],
+        );
+
+        let kinds: Vec<_> = reconciliation
+            .discrepancies
+            .iter()
+            .map(|discrepancy| (discrepancy.client, discrepancy.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (ClientId::new(1), DiscrepancyKind::DuplicateExpected),
+                (ClientId::new(2), DiscrepancyKind::DuplicateActual)
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_deltas_per_field() {
+        let expected = account(1, dec!(10));
+        let actual = Account {
+            is_locked: true,
+            ..account(1, dec!(7.5))
+        };
+
+        let reconciliation = reconcile([expected], [actual]);
+
+        let fields: Vec<_> = reconciliation
+            .discrepancies
+            .iter()
+            .map(|discrepancy| (discrepancy.field, discrepancy.delta))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (Some(AccountField::Available), Some(dec!(-2.5))),
+                (Some(AccountField::Total), Some(dec!(-2.5))),
+                (Some(AccountField::Locked), None),
+            ]
+        );
+    }
+}
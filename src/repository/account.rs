@@ -1,12 +1,19 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound::{Excluded, Unbounded};
 use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use serde::Serialize;
 use tokio::sync::RwLock;
 
 use crate::models::account::Account;
 use crate::models::client::ClientId;
+use crate::repository::Page;
 
 /// This trait is strictly not necessary. I introduced it to showcase a more realistic scenario, in which the engine might be able to use different repository implementations.
 /// Due to this, result types are part of the signature, to indicate potential IO.
@@ -17,7 +24,108 @@ pub trait AccountRepository {
     async fn get_or_new(&self, client_id: ClientId) -> Result<Account>;
     async fn upsert(&self, account: Account) -> Result<()>;
 
-    async fn balances(&self) -> Vec<Account>;
+    /// Streams all accounts in the given order, allowing the consumer to process them incrementally instead of collecting them first.
+    fn balances(&self, order: BalanceOrder) -> BoxStream<'_, Account>;
+
+    /// Returns a page of the accounts matching the query, ordered by client id.
+    async fn query(&self, query: &AccountQuery) -> Result<Page<Account, ClientId>>;
+
+    /// Counts the accounts matching the query. The limit of the query is ignored.
+    async fn count(&self, query: &AccountQuery) -> Result<usize>;
+
+    /// Aggregates the balances across all clients.
+    async fn totals(&self) -> Result<AccountTotals>;
+}
+
+/// The [AccountQuery] combines optional filters, that all have to match, with a cursor based pagination.
+///
+/// ```ignore
+/// // all locked accounts, that still hold funds
+/// let query = AccountQuery::new().locked().with_held();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountQuery {
+    /// Only accounts, that have been locked by a chargeback
+    pub locked: bool,
+    /// Only accounts with held funds, i.e. `held > 0`
+    pub with_held: bool,
+    /// Only accounts with a total strictly below the threshold
+    pub total_below: Option<Decimal>,
+    /// Cursor: only accounts with a greater client id are returned, see [Page::next]
+    pub after: Option<ClientId>,
+    pub limit: usize,
+}
+
+impl Default for AccountQuery {
+    fn default() -> Self {
+        Self {
+            locked: false,
+            with_held: false,
+            total_below: None,
+            after: None,
+            limit: AccountQuery::DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl AccountQuery {
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn locked(mut self) -> Self {
+        self.locked = true;
+        self
+    }
+
+    pub fn with_held(mut self) -> Self {
+        self.with_held = true;
+        self
+    }
+
+    pub fn total_below(mut self, threshold: Decimal) -> Self {
+        self.total_below = Some(threshold);
+        self
+    }
+
+    pub fn after(mut self, client_id: ClientId) -> Self {
+        self.after = Some(client_id);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, account: &Account) -> bool {
+        (!self.locked || account.is_locked)
+            && (!self.with_held || account.held > Decimal::ZERO)
+            && self
+                .total_below
+                .is_none_or(|threshold| account.total < threshold)
+            && self.after.is_none_or(|after| account.client_id > after)
+    }
+}
+
+/// The balances aggregated across all clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AccountTotals {
+    pub accounts: usize,
+    pub locked: usize,
+    pub available: Decimal,
+    pub held: Decimal,
+    pub total: Decimal,
+}
+
+/// The order of the accounts returned by [AccountRepository::balances]. Each order is deterministic, ties are broken by the [ClientId].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BalanceOrder {
+    #[default]
+    ClientId,
+    TotalDesc,
 }
 
 /// I decided to use an RwLock, mainly because its usage is recommended if there is inner IO, e.g. to call a database.
@@ -60,16 +168,51 @@ impl AccountRepository for InMemoryAccountRepository {
         guard.upsert(account)
     }
 
-    async fn balances<'a>(&'a self) -> Vec<Account> {
+    /// The read lock is held until the stream is dropped. As the guard can't be borrowed by an iterator across awaits,
+    /// the last emitted account serves as cursor into the ordered maps for looking up the next one.
+    fn balances(&self, order: BalanceOrder) -> BoxStream<'_, Account> {
+        let inner = Arc::clone(&self.inner);
+
+        stream::once(inner.read_owned())
+            .flat_map(move |guard| {
+                stream::unfold((guard, None), move |(guard, cursor)| {
+                    let next = guard.next_after(order, cursor.as_ref());
+
+                    future::ready(next.map(|acc| (acc, (guard, Some(acc)))))
+                })
+            })
+            .boxed()
+    }
+
+    async fn query(&self, query: &AccountQuery) -> Result<Page<Account, ClientId>> {
+        let guard = self.inner.read().await;
+
+        Ok(guard.query(query))
+    }
+
+    async fn count(&self, query: &AccountQuery) -> Result<usize> {
+        let guard = self.inner.read().await;
+
+        Ok(guard.count(query))
+    }
+
+    async fn totals(&self) -> Result<AccountTotals> {
         let guard = self.inner.read().await;
 
-        guard.balances()
+        Ok(guard.totals)
     }
 }
 
 #[derive(Default)]
 struct Inner {
-    accounts: HashMap<ClientId, Account>,
+    accounts: BTreeMap<ClientId, Account>,
+    // Secondary index for the BalanceOrder::TotalDesc and AccountQuery::total_below
+    by_total: BTreeSet<(Reverse<Decimal>, ClientId)>,
+    // Secondary indexes for the AccountQuery filters
+    locked: BTreeSet<ClientId>,
+    with_held: BTreeSet<ClientId>,
+    // Maintained on every upsert, so that the aggregation doesn't need a scan
+    totals: AccountTotals,
 }
 
 impl Inner {
@@ -78,6 +221,11 @@ impl Inner {
     }
 
     fn upsert(&mut self, account: Account) -> Result<()> {
+        if let Some(previous) = self.get(account.client_id) {
+            self.remove_from_indexes(previous);
+        }
+        self.add_to_indexes(account);
+
         self.accounts
             .entry(account.client_id)
             .and_modify(|occupied| {
@@ -91,7 +239,115 @@ impl Inner {
         Ok(())
     }
 
-    fn balances(&self) -> Vec<Account> {
-        self.accounts.values().copied().collect()
+    fn add_to_indexes(&mut self, account: Account) {
+        self.by_total
+            .insert((Reverse(account.total), account.client_id));
+
+        if account.is_locked {
+            self.locked.insert(account.client_id);
+        }
+        if account.held > Decimal::ZERO {
+            self.with_held.insert(account.client_id);
+        }
+
+        let totals = &mut self.totals;
+        totals.accounts += 1;
+        totals.locked += usize::from(account.is_locked);
+        totals.available = totals.available.saturating_add(account.available);
+        totals.held = totals.held.saturating_add(account.held);
+        totals.total = totals.total.saturating_add(account.total);
+    }
+
+    fn remove_from_indexes(&mut self, account: Account) {
+        self.by_total
+            .remove(&(Reverse(account.total), account.client_id));
+        self.locked.remove(&account.client_id);
+        self.with_held.remove(&account.client_id);
+
+        let totals = &mut self.totals;
+        totals.accounts -= 1;
+        totals.locked -= usize::from(account.is_locked);
+        totals.available = totals.available.saturating_sub(account.available);
+        totals.held = totals.held.saturating_sub(account.held);
+        totals.total = totals.total.saturating_sub(account.total);
+    }
+
+    fn query(&self, query: &AccountQuery) -> Page<Account, ClientId> {
+        let mut matches = self
+            .candidates(query)
+            .filter_map(|client_id| self.get(client_id))
+            .filter(|acc| query.matches(acc));
+
+        let items: Vec<_> = matches.by_ref().take(query.limit).collect();
+        let next = match matches.next() {
+            Some(_) => items.last().map(|acc| acc.client_id),
+            None => None,
+        };
+
+        Page { items, next }
+    }
+
+    fn count(&self, query: &AccountQuery) -> usize {
+        self.candidates(query)
+            .filter_map(|client_id| self.get(client_id))
+            .filter(|acc| query.matches(acc))
+            .count()
+    }
+
+    /// Selects an index matching one of the filters, preferring the flag indexes, which are expected to be small.
+    /// The candidates are ordered by client id and still need to be checked against the remaining filters.
+    fn candidates(&self, query: &AccountQuery) -> Box<dyn Iterator<Item = ClientId> + '_> {
+        let lower = query.after.map_or(Unbounded, Excluded);
+
+        if query.locked {
+            return Box::new(self.locked.range((lower, Unbounded)).copied());
+        }
+        if query.with_held {
+            return Box::new(self.with_held.range((lower, Unbounded)).copied());
+        }
+
+        match query.total_below {
+            // The index is ordered by total descending, thus the accounts below the threshold are at its end
+            Some(threshold) => {
+                let below = (
+                    Excluded((Reverse(threshold), ClientId::new(u16::MAX))),
+                    Unbounded,
+                );
+                let mut client_ids: Vec<_> = self
+                    .by_total
+                    .range(below)
+                    .map(|(_, client_id)| *client_id)
+                    .filter(|client_id| query.after.is_none_or(|after| *client_id > after))
+                    .collect();
+                client_ids.sort_unstable();
+
+                Box::new(client_ids.into_iter())
+            }
+            None => Box::new(self.accounts.range((lower, Unbounded)).map(|(id, _)| *id)),
+        }
+    }
+
+    fn next_after(&self, order: BalanceOrder, cursor: Option<&Account>) -> Option<Account> {
+        match order {
+            BalanceOrder::ClientId => {
+                let lower = cursor.map_or(Unbounded, |acc| Excluded(acc.client_id));
+
+                self.accounts
+                    .range((lower, Unbounded))
+                    .next()
+                    .map(|(_, acc)| *acc)
+            }
+
+            BalanceOrder::TotalDesc => {
+                let lower = cursor.map_or(Unbounded, |acc| {
+                    Excluded((Reverse(acc.total), acc.client_id))
+                });
+
+                self.by_total
+                    .range((lower, Unbounded))
+                    .next()
+                    .and_then(|(_, client_id)| self.get(*client_id))
+            }
+        }
     }
 }
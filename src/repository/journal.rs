@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use tokio::sync::RwLock;
+
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::journal::{
+    JournalEntry, Posting, SubLedger, TrialBalance, TrialBalanceLine, is_balanced,
+};
+use crate::models::transaction::TxRecord;
+
+/// The double-entry journal of the applied records, see [crate::models::journal::postings_for].
+///
+/// The journal is a parallel check, not the source of truth: the engine still applies every record to the [crate::prelude::AccountRepository]
+/// and posts it to the journal afterwards. A failing post doesn't undo the record, thus the journal may miss records, see [crate::prelude::EngineMetrics] for the sink failures.
+/// Comparing [JournalRepository::accounts] with the account balances, like the `trial-balance` command does, proves that both agree.
+#[async_trait]
+pub trait JournalRepository: Send + Sync {
+    /// Posts the postings of an applied record as new entry. Unbalanced postings are rejected.
+    async fn post(&self, record: TxRecord, postings: Vec<Posting>) -> Result<JournalEntry>;
+
+    /// Streams the entries in sequence order.
+    fn entries(&self) -> BoxStream<'_, JournalEntry>;
+
+    /// The balance of every sub-ledger, that has been posted to.
+    async fn trial_balance(&self) -> Result<TrialBalance>;
+
+    /// Projects the client accounts from the client sub-ledgers, ordered by client. The projection is meant to be compared with the account repository, it doesn't replace it.
+    /// Accounts, that have never been posted to, e.g. opened by a failed withdrawal, are not part of the projection.
+    async fn accounts(&self) -> Result<Vec<Account>>;
+}
+
+/// Keeps all entries in memory. The balances of the sub-ledgers are maintained on every post.
+#[derive(Default, Clone)]
+pub struct InMemoryJournal {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl InMemoryJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl JournalRepository for InMemoryJournal {
+    async fn post(&self, record: TxRecord, postings: Vec<Posting>) -> Result<JournalEntry> {
+        let mut guard = self.inner.write().await;
+
+        guard.post(record, postings)
+    }
+
+    fn entries(&self) -> BoxStream<'_, JournalEntry> {
+        let inner = Arc::clone(&self.inner);
+
+        stream::once(inner.read_owned())
+            .flat_map(|guard| {
+                stream::unfold((guard, 0), |(guard, pos)| {
+                    let next = guard.entries.get(pos).cloned();
+
+                    future::ready(next.map(|entry| (entry, (guard, pos + 1))))
+                })
+            })
+            .boxed()
+    }
+
+    async fn trial_balance(&self) -> Result<TrialBalance> {
+        let guard = self.inner.read().await;
+
+        let lines = guard
+            .balances
+            .iter()
+            .map(|(sub_ledger, balance)| TrialBalanceLine {
+                sub_ledger: *sub_ledger,
+                balance: *balance,
+            })
+            .collect();
+
+        Ok(TrialBalance { lines })
+    }
+
+    async fn accounts(&self) -> Result<Vec<Account>> {
+        let guard = self.inner.read().await;
+
+        Ok(guard.accounts())
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: Vec<JournalEntry>,
+    // The debit-positive balance of each sub-ledger, ordered by the sub-ledger
+    balances: BTreeMap<SubLedger, Decimal>,
+    // A chargeback locks the account, which is not reflected by any balance
+    locked: BTreeSet<ClientId>,
+}
+
+impl Inner {
+    fn post(&mut self, record: TxRecord, postings: Vec<Posting>) -> Result<JournalEntry> {
+        if !is_balanced(&postings) {
+            bail!("Failed to post the unbalanced postings of the record: {record:?}");
+        }
+
+        for posting in &postings {
+            let balance = self.balances.entry(posting.sub_ledger).or_default();
+            *balance = balance.saturating_add(posting.amount);
+        }
+
+        if let TxRecord::Chargeback(cb) = record {
+            self.locked.insert(cb.client_id);
+        }
+
+        let entry = JournalEntry {
+            seq: self.entries.len() as u64 + 1,
+            record,
+            postings,
+        };
+        self.entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        let mut accounts: BTreeMap<ClientId, Account> = BTreeMap::new();
+
+        for (sub_ledger, balance) in &self.balances {
+            let Some(client_id) = sub_ledger.client_id() else {
+                continue;
+            };
+            let acc = accounts
+                .entry(client_id)
+                .or_insert_with(|| Account::new(client_id));
+
+            // The client sub-ledgers are credit-normal, thus the debit-positive balance is negated
+            match sub_ledger {
+                SubLedger::ClientAvailable(_) => acc.available = -*balance,
+                SubLedger::ClientHeld(_) => acc.held = -*balance,
+                SubLedger::FiatClearing | SubLedger::ChargebackLoss => {}
+            }
+            acc.total = acc.available.saturating_add(acc.held);
+        }
+
+        for client_id in &self.locked {
+            accounts
+                .entry(*client_id)
+                .or_insert_with(|| Account::new(*client_id))
+                .is_locked = true;
+        }
+
+        accounts.into_values().collect()
+    }
+}
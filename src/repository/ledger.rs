@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::sync::RwLock;
+
+use crate::models::account::Account;
+use crate::models::client::ClientId;
+use crate::models::ledger::{LedgerEntry, Outcome};
+use crate::models::transaction::TxRecord;
+
+/// The append-only log of every record processed by the engine, see [LedgerEntry].
+///
+/// The ledger owns the sequence, thus an entry gets its sequence number by being appended.
+#[async_trait]
+pub trait LedgerRepository: Send + Sync {
+    /// Appends a new entry with the next sequence number and returns it.
+    async fn append(
+        &self,
+        record: TxRecord,
+        outcome: Outcome,
+        before: Option<Account>,
+        after: Option<Account>,
+    ) -> Result<LedgerEntry>;
+
+    /// Streams the entries in sequence order, optionally filtered by client.
+    fn entries(&self, client_id: Option<ClientId>) -> BoxStream<'_, LedgerEntry>;
+}
+
+/// Keeps all entries in memory, thus the memory grows with every processed record.
+#[derive(Default, Clone)]
+pub struct InMemoryLedger {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl InMemoryLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for InMemoryLedger {
+    async fn append(
+        &self,
+        record: TxRecord,
+        outcome: Outcome,
+        before: Option<Account>,
+        after: Option<Account>,
+    ) -> Result<LedgerEntry> {
+        let mut guard = self.inner.write().await;
+
+        Ok(guard.append(record, outcome, before, after))
+    }
+
+    /// Like the other repositories, the read lock is held until the stream is dropped and the position serves as cursor.
+    fn entries(&self, client_id: Option<ClientId>) -> BoxStream<'_, LedgerEntry> {
+        let inner = Arc::clone(&self.inner);
+
+        stream::once(inner.read_owned())
+            .flat_map(move |guard| {
+                stream::unfold((guard, 0), move |(guard, pos)| {
+                    let next = guard.nth_entry(client_id, pos);
+
+                    future::ready(next.map(|entry| (entry, (guard, pos + 1))))
+                })
+            })
+            .boxed()
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: Vec<LedgerEntry>,
+    // Secondary index of positions in the entries per client
+    by_client: BTreeMap<ClientId, Vec<usize>>,
+}
+
+impl Inner {
+    fn append(
+        &mut self,
+        record: TxRecord,
+        outcome: Outcome,
+        before: Option<Account>,
+        after: Option<Account>,
+    ) -> LedgerEntry {
+        let pos = self.entries.len();
+        let entry = LedgerEntry {
+            seq: pos as u64 + 1,
+            record,
+            outcome,
+            before,
+            after,
+        };
+
+        self.by_client
+            .entry(record.client_id())
+            .or_default()
+            .push(pos);
+        self.entries.push(entry.clone());
+
+        entry
+    }
+
+    fn nth_entry(&self, client_id: Option<ClientId>, n: usize) -> Option<LedgerEntry> {
+        let pos = match client_id {
+            None => n,
+            Some(client_id) => *self.by_client.get(&client_id)?.get(n)?,
+        };
+
+        self.entries.get(pos).cloned()
+    }
+}
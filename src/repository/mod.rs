@@ -1,2 +1,15 @@
 pub(crate) mod account;
+pub(crate) mod journal;
+pub(crate) mod ledger;
+pub(crate) mod seen_index;
 pub(crate) mod transaction;
+pub(crate) mod tx_id_set;
+
+use serde::Serialize;
+
+/// A page of a query result. If there are more results, [Page::next] holds the cursor for querying the next page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Page<T, C> {
+    pub items: Vec<T>,
+    pub next: Option<C>,
+}
@@ -0,0 +1,129 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use roaring::RoaringBitmap;
+
+use crate::models::transaction::TransactionId;
+use crate::repository::tx_id_set::TxIdSet;
+
+/// The [SeenTxIndex] answers the question "has this tx id been used before?" without touching the [crate::prelude::TransactionRepository].
+///
+/// The engine consults it in the replay check for deposits and withdrawals. Only if an id has been seen before, the full transaction is loaded,
+/// e.g. to decide whether a failed transaction may be retried. As new ids are the common case, this saves a lookup of the full transaction for almost every record.
+///
+/// In contrast to the repositories, the index is synchronous. Implementations are expected to be compact in-memory structures.
+pub trait SeenTxIndex: Send + Sync {
+    /// Marks the id as seen. Returns `true`, if it has not been seen before
+    fn insert(&self, tx_id: TransactionId) -> bool;
+    fn contains(&self, tx_id: TransactionId) -> bool;
+
+    /// The number of distinct ids seen
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The approximated memory footprint in bytes
+    fn memory_bytes(&self) -> usize;
+}
+
+/// A [SeenTxIndex] backed by a paged bitset over the `u32` id space. Lookups are a constant time bit test.
+/// The memory is bounded by 512 MiB, while each 65536 consecutive ids in use allocate a page of 8 KiB.
+#[derive(Default)]
+pub struct BitmapSeenIndex {
+    inner: Mutex<TxIdSet>,
+}
+
+impl BitmapSeenIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TxIdSet> {
+        // The set can't be left in an inconsistent state, thus it is safe to ignore the poisoning
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SeenTxIndex for BitmapSeenIndex {
+    fn insert(&self, tx_id: TransactionId) -> bool {
+        self.lock().insert(tx_id)
+    }
+
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        self.lock().contains(tx_id)
+    }
+
+    fn len(&self) -> u64 {
+        self.lock().len()
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.lock().allocated_bytes()
+    }
+}
+
+/// A [SeenTxIndex] backed by a roaring bitmap. It compresses sparse and dense id ranges alike,
+/// at the expense of a slightly slower lookup compared to the [BitmapSeenIndex].
+#[derive(Default)]
+pub struct RoaringSeenIndex {
+    inner: Mutex<RoaringBitmap>,
+}
+
+impl RoaringSeenIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RoaringBitmap> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SeenTxIndex for RoaringSeenIndex {
+    fn insert(&self, tx_id: TransactionId) -> bool {
+        self.lock().insert(tx_id.into_inner())
+    }
+
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        self.lock().contains(tx_id.into_inner())
+    }
+
+    fn len(&self) -> u64 {
+        self.lock().len()
+    }
+
+    fn memory_bytes(&self) -> usize {
+        // The serialized size is a close approximation of the size of the containers
+        self.lock().serialized_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_behaves_as_set(index: &dyn SeenTxIndex) {
+        assert!(index.is_empty());
+
+        assert!(index.insert(TransactionId::new(1)));
+        assert!(index.insert(TransactionId::new(u32::MAX)));
+        assert!(!index.insert(TransactionId::new(1)));
+
+        assert!(index.contains(TransactionId::new(1)));
+        assert!(index.contains(TransactionId::new(u32::MAX)));
+        assert!(!index.contains(TransactionId::new(2)));
+        assert_eq!(index.len(), 2);
+        assert!(index.memory_bytes() > 0);
+    }
+
+    #[test]
+    fn bitmap_index_behaves_as_set() {
+        assert_behaves_as_set(&BitmapSeenIndex::new());
+    }
+
+    #[test]
+    fn roaring_index_behaves_as_set() {
+        assert_behaves_as_set(&RoaringSeenIndex::new());
+    }
+}
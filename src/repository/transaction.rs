@@ -1,19 +1,134 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::iter::{self, Peekable};
+use std::ops::Bound::{Excluded, Included, Unbounded};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt};
 use tokio::sync::RwLock;
+use tokio::time::Instant;
 
-use crate::models::transaction::{Transaction, TransactionId, TransactionStatus};
+use crate::models::client::ClientId;
+use crate::models::transaction::{Transaction, TransactionId, TransactionStatus, TransactionType};
+use crate::repository::Page;
+use crate::repository::tx_id_set::TxIdSet;
 
 /// This trait is strictly not necessary. I introduced it to showcase a more realistic scenario, in which the engine might be able to use different repository implementations.
 /// Due to this, result types are part of the signature, to indicate potential IO.
 #[async_trait]
 pub trait TransactionRepository: Send + Sync {
     async fn get(&self, tx_id: TransactionId) -> Option<Transaction>;
+
+    /// Returns `true`, if the transaction has been persisted, but is not available anymore, e.g. because it left a retention window.
+    /// The default implementation covers repositories that keep every transaction.
+    async fn is_expired(&self, _tx_id: TransactionId) -> bool {
+        false
+    }
+
     async fn insert(&self, tx: Transaction) -> Result<()>;
+    /// In contrast to [TransactionRepository::insert], an existing transaction with the same id is replaced.
+    async fn upsert(&self, tx: Transaction) -> Result<()>;
     async fn update_status(&self, tx_id: TransactionId, status: TransactionStatus) -> Result<()>;
+
+    /// Streams the stored transactions, optionally filtered by client. They are ordered by client and by tx id within a client.
+    fn history(&self, client_id: Option<ClientId>) -> BoxStream<'_, Transaction>;
+
+    /// Returns a page of the transactions matching the query, ordered by tx id.
+    async fn query(&self, query: &TxQuery) -> Result<Page<Transaction, TransactionId>>;
+
+    /// Counts the transactions matching the query. The limit of the query is ignored.
+    async fn count(&self, query: &TxQuery) -> Result<usize>;
+}
+
+/// The [TxQuery] combines optional filters, that all have to match, with a cursor based pagination.
+///
+/// ```ignore
+/// // all open disputes of client 1
+/// let query = TxQuery::new().client(ClientId::new(1)).status(TransactionStatus::Disputed);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxQuery {
+    pub client_id: Option<ClientId>,
+    pub status: Option<TransactionStatus>,
+    pub tx_type: Option<TransactionType>,
+    /// Cursor: only transactions with a greater tx id are returned, see [Page::next]
+    pub after: Option<TransactionId>,
+    pub limit: usize,
+}
+
+impl Default for TxQuery {
+    fn default() -> Self {
+        Self {
+            client_id: None,
+            status: None,
+            tx_type: None,
+            after: None,
+            limit: TxQuery::DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl TxQuery {
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client_id: ClientId) -> Self {
+        self.client_id = Some(client_id);
+        self
+    }
+
+    pub fn status(mut self, status: TransactionStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn tx_type(mut self, tx_type: TransactionType) -> Self {
+        self.tx_type = Some(tx_type);
+        self
+    }
+
+    pub fn after(mut self, tx_id: TransactionId) -> Self {
+        self.after = Some(tx_id);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        self.client_id
+            .is_none_or(|client_id| tx.client_id == client_id)
+            && self.status.is_none_or(|status| tx.status == status)
+            && self.tx_type.is_none_or(|tx_type| tx.tx_type == tx_type)
+            && self.after.is_none_or(|after| tx.id > after)
+    }
+
+    fn has_filter(&self) -> bool {
+        self.client_id.is_some() || self.status.is_some() || self.tx_type.is_some()
+    }
+}
+
+/// The [TxRetention] bounds the memory of the [InMemoryTxRepository].
+///
+/// Full transactions are only kept within the retention window, as only those can still be referenced by a dispute, resolve or chargeback.
+/// Ids of transactions leaving the window are tracked in a compact bitset, so that duplicates are still rejected exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TxRetention {
+    /// Every transaction is kept forever. This requires memory proportional to the number of transactions.
+    #[default]
+    Unbounded,
+    /// Only the most recent transactions are kept.
+    Count(usize),
+    /// Only transactions inserted within the given duration are kept.
+    Duration(Duration),
 }
 
 /// I decided to use an RwLock, mainly because its usage is recommended if there is inner IO, e.g. to call a database.
@@ -28,6 +143,17 @@ impl InMemoryTxRepository {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn with_retention(retention: TxRetention) -> Self {
+        let inner = Inner {
+            retention,
+            ..Inner::default()
+        };
+
+        Self {
+            inner: Arc::new(RwLock::new(inner)),
+        }
+    }
 }
 
 #[async_trait]
@@ -38,48 +164,336 @@ impl TransactionRepository for InMemoryTxRepository {
         guard.get(tx_id)
     }
 
+    async fn is_expired(&self, tx_id: TransactionId) -> bool {
+        let guard = self.inner.read().await;
+
+        guard.is_expired(tx_id)
+    }
+
     async fn insert(&self, tx: Transaction) -> Result<()> {
         let mut guard = self.inner.write().await;
 
         guard.insert(tx)
     }
 
+    async fn upsert(&self, tx: Transaction) -> Result<()> {
+        let mut guard = self.inner.write().await;
+
+        guard.upsert(tx)
+    }
+
     async fn update_status(&self, tx_id: TransactionId, status: TransactionStatus) -> Result<()> {
         let mut guard = self.inner.write().await;
 
         guard.update_status(tx_id, status)
     }
+
+    async fn query(&self, query: &TxQuery) -> Result<Page<Transaction, TransactionId>> {
+        let guard = self.inner.read().await;
+
+        Ok(guard.query(query))
+    }
+
+    async fn count(&self, query: &TxQuery) -> Result<usize> {
+        let guard = self.inner.read().await;
+
+        Ok(guard.count(query))
+    }
+
+    /// Only transactions within the retention window are part of the history.
+    /// Like for the account balances, the read lock is held until the stream is dropped and the last transaction serves as cursor.
+    fn history(&self, client_id: Option<ClientId>) -> BoxStream<'_, Transaction> {
+        let inner = Arc::clone(&self.inner);
+
+        stream::once(inner.read_owned())
+            .flat_map(move |guard| {
+                stream::unfold((guard, None), move |(guard, cursor)| {
+                    let next = guard.next_in_history(client_id, cursor);
+
+                    future::ready(next.map(|tx| (tx, (guard, Some((tx.client_id, tx.id))))))
+                })
+            })
+            .boxed()
+    }
+}
+
+struct Retained {
+    tx: Transaction,
+    inserted_at: Instant,
 }
 
 #[derive(Default)]
 struct Inner {
-    txs: HashMap<TransactionId, Transaction>,
+    retention: TxRetention,
+    txs: HashMap<TransactionId, Retained>,
+    // Insertion order of the retained transactions, the oldest one is in front
+    order: VecDeque<TransactionId>,
+    // Secondary indexes of the retained transactions
+    by_client: BTreeMap<ClientId, BTreeSet<TransactionId>>,
+    by_status: BTreeMap<TransactionStatus, BTreeSet<TransactionId>>,
+    by_type: BTreeMap<TransactionType, BTreeSet<TransactionId>>,
+    expired: TxIdSet,
 }
 
 impl Inner {
     fn get(&self, tx_id: TransactionId) -> Option<Transaction> {
-        self.txs.get(&tx_id).copied()
+        self.txs
+            .get(&tx_id)
+            .filter(|retained| self.is_retained(retained))
+            .map(|retained| retained.tx)
+    }
+
+    fn is_expired(&self, tx_id: TransactionId) -> bool {
+        self.expired.contains(tx_id)
+            || self
+                .txs
+                .get(&tx_id)
+                .is_some_and(|retained| !self.is_retained(retained))
     }
 
     fn insert(&mut self, tx: Transaction) -> Result<()> {
-        if self.txs.contains_key(&tx.id) {
+        self.evict();
+
+        if self.txs.contains_key(&tx.id) || self.expired.contains(tx.id) {
             bail!(
                 "Failed to insert transaction with id: {:?}. A transaction with the same key has already been persisted",
                 tx.id
             );
         }
 
-        self.txs.insert(tx.id, tx);
+        self.retain(tx);
+
+        Ok(())
+    }
+
+    fn upsert(&mut self, tx: Transaction) -> Result<()> {
+        self.evict();
+
+        if self.expired.contains(tx.id) {
+            bail!(
+                "Failed to replace transaction with id: {:?}, because it has already left the retention window",
+                tx.id
+            );
+        }
+
+        match self.txs.get_mut(&tx.id) {
+            Some(retained) => {
+                let previous = std::mem::replace(&mut retained.tx, tx);
+                self.remove_from_indexes(previous);
+                self.add_to_indexes(tx);
+            }
+            None => self.retain(tx),
+        }
 
         Ok(())
     }
 
     fn update_status(&mut self, tx_id: TransactionId, status: TransactionStatus) -> Result<()> {
-        self.txs
-            .get_mut(&tx_id)
-            .map(|tx| tx.status = status)
-            .with_context(|| {
-                format!("Failed to resolve transaction: {tx_id:?} and status: {status:?}")
+        if self.is_expired(tx_id) {
+            bail!(
+                "Failed to update transaction: {tx_id:?} to status: {status:?}, because the dispute window expired"
+            );
+        }
+
+        let retained = self.txs.get_mut(&tx_id).with_context(|| {
+            format!("Failed to resolve transaction: {tx_id:?} and status: {status:?}")
+        })?;
+
+        let previous = std::mem::replace(&mut retained.tx.status, status);
+        index_remove(&mut self.by_status, previous, tx_id);
+        index_insert(&mut self.by_status, status, tx_id);
+
+        Ok(())
+    }
+
+    fn retain(&mut self, tx: Transaction) {
+        self.txs.insert(
+            tx.id,
+            Retained {
+                tx,
+                inserted_at: Instant::now(),
+            },
+        );
+        self.order.push_back(tx.id);
+        self.add_to_indexes(tx);
+
+        // Enforces the count based retention immediately, the time based one is checked lazily
+        self.evict();
+    }
+
+    fn add_to_indexes(&mut self, tx: Transaction) {
+        index_insert(&mut self.by_client, tx.client_id, tx.id);
+        index_insert(&mut self.by_status, tx.status, tx.id);
+        index_insert(&mut self.by_type, tx.tx_type, tx.id);
+    }
+
+    fn remove_from_indexes(&mut self, tx: Transaction) {
+        index_remove(&mut self.by_client, tx.client_id, tx.id);
+        index_remove(&mut self.by_status, tx.status, tx.id);
+        index_remove(&mut self.by_type, tx.tx_type, tx.id);
+    }
+
+    fn query(&self, query: &TxQuery) -> Page<Transaction, TransactionId> {
+        let mut matches = self
+            .candidates(query)
+            .filter_map(|tx_id| self.get(tx_id))
+            .filter(|tx| query.matches(tx));
+
+        let items: Vec<_> = matches.by_ref().take(query.limit).collect();
+        let next = match matches.next() {
+            Some(_) => items.last().map(|tx| tx.id),
+            None => None,
+        };
+
+        Page { items, next }
+    }
+
+    fn count(&self, query: &TxQuery) -> usize {
+        // The index sizes are exact, unless time based retention keeps expired transactions until their eviction
+        let is_exact = !matches!(self.retention, TxRetention::Duration(_)) && query.after.is_none();
+        let filters = [
+            query
+                .client_id
+                .map(|client_id| self.by_client.get(&client_id)),
+            query.status.map(|status| self.by_status.get(&status)),
+            query.tx_type.map(|tx_type| self.by_type.get(&tx_type)),
+        ];
+
+        match filters.into_iter().flatten().collect::<Vec<_>>().as_slice() {
+            [] if is_exact => self.txs.len(),
+            [tx_ids] if is_exact => tx_ids.map_or(0, BTreeSet::len),
+            _ => self
+                .candidates(query)
+                .filter_map(|tx_id| self.get(tx_id))
+                .filter(|tx| query.matches(tx))
+                .count(),
+        }
+    }
+
+    /// Selects the smallest index matching one of the filters. The candidates still need to be checked against the remaining filters.
+    /// Without any filter, the type indexes are merged, as they contain all transactions.
+    fn candidates(&self, query: &TxQuery) -> Box<dyn Iterator<Item = TransactionId> + '_> {
+        let lower = query.after.map_or(Unbounded, Excluded);
+
+        if !query.has_filter() {
+            let ranges = self
+                .by_type
+                .values()
+                .map(|tx_ids| tx_ids.range((lower, Unbounded)).copied().peekable())
+                .collect();
+
+            return Box::new(merge_ascending(ranges));
+        }
+
+        let filters = [
+            query
+                .client_id
+                .map(|client_id| self.by_client.get(&client_id)),
+            query.status.map(|status| self.by_status.get(&status)),
+            query.tx_type.map(|tx_type| self.by_type.get(&tx_type)),
+        ];
+        let smallest = filters
+            .into_iter()
+            .flatten()
+            .min_by_key(|tx_ids| tx_ids.map_or(0, BTreeSet::len))
+            .flatten();
+
+        match smallest {
+            Some(tx_ids) => Box::new(tx_ids.range((lower, Unbounded)).copied()),
+            // One of the filters has no matching transactions at all
+            None => Box::new(iter::empty()),
+        }
+    }
+
+    fn next_in_history(
+        &self,
+        client_id: Option<ClientId>,
+        cursor: Option<(ClientId, TransactionId)>,
+    ) -> Option<Transaction> {
+        let clients = match (client_id, cursor) {
+            (Some(client_id), _) => (Included(client_id), Included(client_id)),
+            (None, Some((client_id, _))) => (Included(client_id), Unbounded),
+            (None, None) => (Unbounded, Unbounded),
+        };
+
+        self.by_client
+            .range(clients)
+            .find_map(|(client_id, tx_ids)| {
+                let lower = match cursor {
+                    Some((cursor_client, tx_id)) if cursor_client == *client_id => Excluded(tx_id),
+                    _ => Unbounded,
+                };
+
+                tx_ids
+                    .range((lower, Unbounded))
+                    .find_map(|tx_id| self.get(*tx_id))
             })
     }
+
+    fn is_retained(&self, retained: &Retained) -> bool {
+        match self.retention {
+            TxRetention::Duration(window) => retained.inserted_at.elapsed() <= window,
+            TxRetention::Unbounded | TxRetention::Count(_) => true,
+        }
+    }
+
+    /// Moves the transactions, that left the retention window, from the map and the indexes into the compact bitset
+    fn evict(&mut self) {
+        while let Some(&oldest) = self.order.front() {
+            let is_outside = match self.retention {
+                TxRetention::Unbounded => false,
+                TxRetention::Count(count) => self.order.len() > count,
+                TxRetention::Duration(window) => self
+                    .txs
+                    .get(&oldest)
+                    .is_none_or(|retained| retained.inserted_at.elapsed() > window),
+            };
+
+            if !is_outside {
+                break;
+            }
+
+            self.order.pop_front();
+            self.expired.insert(oldest);
+            if let Some(retained) = self.txs.remove(&oldest) {
+                self.remove_from_indexes(retained.tx);
+            }
+        }
+    }
+}
+
+fn index_insert<K: Ord>(
+    index: &mut BTreeMap<K, BTreeSet<TransactionId>>,
+    key: K,
+    tx_id: TransactionId,
+) {
+    index.entry(key).or_default().insert(tx_id);
+}
+
+fn index_remove<K: Ord>(
+    index: &mut BTreeMap<K, BTreeSet<TransactionId>>,
+    key: K,
+    tx_id: TransactionId,
+) {
+    if let Some(tx_ids) = index.get_mut(&key) {
+        tx_ids.remove(&tx_id);
+
+        if tx_ids.is_empty() {
+            index.remove(&key);
+        }
+    }
+}
+
+/// Merges ascending iterators of disjoint ids into a single ascending iterator
+fn merge_ascending<I>(mut iters: Vec<Peekable<I>>) -> impl Iterator<Item = TransactionId>
+where
+    I: Iterator<Item = TransactionId>,
+{
+    iter::from_fn(move || {
+        iters
+            .iter_mut()
+            .filter_map(|iter| iter.peek().copied().map(|tx_id| (tx_id, iter)))
+            .min_by_key(|(tx_id, _)| *tx_id)
+            .and_then(|(_, iter)| iter.next())
+    })
 }
@@ -0,0 +1,114 @@
+use crate::models::transaction::TransactionId;
+
+/// Number of bits per page. A page covers 65536 consecutive tx ids and takes 8 KiB.
+const PAGE_BITS: usize = 1 << 16;
+const WORDS_PER_PAGE: usize = PAGE_BITS / u64::BITS as usize;
+const PAGE_COUNT: usize = (u32::MAX as usize + 1) / PAGE_BITS;
+
+/// A compact and exact set of [TransactionId]s, implemented as a bitset over the entire `u32` id space.
+///
+/// The bitset is split into pages, that are only allocated once an id within their range is inserted.
+/// This bounds the memory to 512 MiB for the worst case, while dense or clustered ids (the common case) only need a fraction of it.
+#[derive(Default)]
+pub(crate) struct TxIdSet {
+    pages: Vec<Option<Box<[u64]>>>,
+    len: u64,
+}
+
+impl TxIdSet {
+    /// Returns `true`, if the id has not been present before
+    pub(crate) fn insert(&mut self, tx_id: TransactionId) -> bool {
+        let (page, word, mask) = position(tx_id);
+
+        if self.pages.is_empty() {
+            self.pages.resize_with(PAGE_COUNT, || None);
+        }
+
+        let words = self.pages[page].get_or_insert_with(|| vec![0; WORDS_PER_PAGE].into());
+        let is_new = words[word] & mask == 0;
+        words[word] |= mask;
+
+        if is_new {
+            self.len += 1;
+        }
+
+        is_new
+    }
+
+    pub(crate) fn contains(&self, tx_id: TransactionId) -> bool {
+        let (page, word, mask) = position(tx_id);
+
+        self.pages
+            .get(page)
+            .and_then(Option::as_ref)
+            .is_some_and(|words| words[word] & mask != 0)
+    }
+
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// The number of bytes allocated for the page table and the pages.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        let page_bytes = WORDS_PER_PAGE * size_of::<u64>();
+        let allocated_pages = self.pages.iter().filter(|page| page.is_some()).count();
+
+        self.pages.capacity() * size_of::<Option<Box<[u64]>>>() + allocated_pages * page_bytes
+    }
+}
+
+fn position(tx_id: TransactionId) -> (usize, usize, u64) {
+    let id = tx_id.into_inner() as usize;
+    let page = id / PAGE_BITS;
+    let bit = id % PAGE_BITS;
+
+    (
+        page,
+        bit / u64::BITS as usize,
+        1 << (bit % u64::BITS as usize),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_only_inserted_ids() {
+        let mut set = TxIdSet::default();
+
+        assert!(set.insert(TransactionId::new(0)));
+        assert!(set.insert(TransactionId::new(65_537)));
+        assert!(set.insert(TransactionId::new(u32::MAX)));
+
+        assert!(set.contains(TransactionId::new(0)));
+        assert!(set.contains(TransactionId::new(65_537)));
+        assert!(set.contains(TransactionId::new(u32::MAX)));
+        assert!(!set.contains(TransactionId::new(1)));
+        assert!(!set.contains(TransactionId::new(65_536)));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn inserting_an_id_twice_is_detected() {
+        let mut set = TxIdSet::default();
+
+        assert!(set.insert(TransactionId::new(42)));
+        assert!(!set.insert(TransactionId::new(42)));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn allocates_pages_lazily() {
+        let mut set = TxIdSet::default();
+        assert_eq!(set.allocated_bytes(), 0);
+
+        (0..1000).for_each(|id| {
+            set.insert(TransactionId::new(id));
+        });
+        let one_page = set.allocated_bytes();
+
+        set.insert(TransactionId::new(u32::MAX));
+        assert_eq!(set.allocated_bytes() - one_page, PAGE_BITS / 8);
+    }
+}
@@ -0,0 +1,234 @@
+use std::num::NonZeroUsize;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::csv::CsvDecoder;
+use crate::engine::{PaymentEngine, RecordOutcome};
+use crate::models::transaction::TxRecord;
+use crate::repository::account::AccountRepository;
+use crate::repository::transaction::TransactionRepository;
+
+/// The number of records of a single connection, that may wait for their ack at the same time.
+const IN_FLIGHT_PER_CONNECTION: usize = 64;
+
+/// The encoding of the records on a connection, one record per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFormat {
+    /// A CSV record without header, e.g. `deposit,1,1,1.5`. A header line is skipped.
+    Csv,
+    /// A JSON object, e.g. `{"type":"deposit","client":1,"tx":1,"amount":"1.5"}`
+    Json,
+}
+
+impl LineFormat {
+    pub(crate) fn decode(self, line: &str) -> Result<TxRecord> {
+        match self {
+            LineFormat::Csv => CsvDecoder::decode_line(line),
+            LineFormat::Json => {
+                serde_json::from_str(line).context("Failed to deserialize JSON into TxRecord")
+            }
+        }
+    }
+
+    fn is_header(self, line: &str) -> bool {
+        self == LineFormat::Csv && line.starts_with("type")
+    }
+}
+
+type Submission = (TxRecord, oneshot::Sender<RecordOutcome>);
+
+/// Merges the records of many producers into a single engine.
+///
+/// The engine runs in its own task and processes the records one at a time, in the order they arrive in the channel.
+/// The channel is bounded, thus a producer has to wait, if the engine falls behind. For a connection, this means that no further lines are read, until there is capacity again.
+#[derive(Debug, Clone)]
+pub struct Ingest {
+    sender: mpsc::Sender<Submission>,
+}
+
+impl Ingest {
+    /// Spawns the task of the engine. It ends, when every clone of the [Ingest] has been dropped, and returns the engine for reading the results.
+    pub fn spawn<AR, TR>(
+        engine: PaymentEngine<AR, TR>,
+        capacity: NonZeroUsize,
+    ) -> (Self, JoinHandle<PaymentEngine<AR, TR>>)
+    where
+        AR: AccountRepository + Send + Sync + 'static,
+        TR: TransactionRepository + 'static,
+    {
+        let (sender, mut receiver) = mpsc::channel::<Submission>(capacity.get());
+
+        let task = tokio::spawn(async move {
+            while let Some((record, ack)) = receiver.recv().await {
+                let outcome = engine.submit(record).await;
+                // The producer might have gone in the meantime, but the record has been processed anyway
+                let _ = ack.send(outcome);
+            }
+
+            engine
+        });
+
+        (Self { sender }, task)
+    }
+
+    /// Waits for capacity, submits the record and returns the receiver of its outcome.
+    async fn enqueue(&self, record: TxRecord) -> Result<oneshot::Receiver<RecordOutcome>> {
+        let (ack, outcome) = oneshot::channel();
+        self.sender
+            .send((record, ack))
+            .await
+            .map_err(|_| anyhow!("Failed to submit the record, because the engine stopped"))?;
+
+        Ok(outcome)
+    }
+
+    pub async fn submit(&self, record: TxRecord) -> Result<RecordOutcome> {
+        self.enqueue(record)
+            .await?
+            .await
+            .context("Failed to receive the outcome, because the engine stopped")
+    }
+}
+
+/// The answer for a line, written as JSON line, e.g. `{"line":1,"outcome":"applied","account":{...}}`.
+/// Lines are counted from 1, including skipped header and empty lines.
+#[derive(Debug, Serialize)]
+struct Ack {
+    line: u64,
+    #[serde(flatten)]
+    outcome: AckOutcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum AckOutcome {
+    Record(RecordOutcome),
+    Malformed(Malformed),
+}
+
+/// The line could not be decoded, thus it never reached the engine
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename = "malformed")]
+struct Malformed {
+    message: String,
+}
+
+enum Pending {
+    Submitted(oneshot::Receiver<RecordOutcome>),
+    Malformed(String),
+}
+
+/// Reads the records of a connection line by line and acks every record with its outcome, in the order of the lines.
+///
+/// Reading and acking run concurrently, thus a producer doesn't need to wait for the ack, before sending the next line.
+pub async fn serve_connection<S>(stream: S, ingest: Ingest, format: LineFormat) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, writer) = tokio::io::split(stream);
+    let (pending_tx, mut pending_rx) = mpsc::channel::<(u64, Pending)>(IN_FLIGHT_PER_CONNECTION);
+
+    let read = async move {
+        let mut lines = BufReader::new(reader).lines();
+        let mut line_no = 0;
+
+        while let Some(line) = lines.next_line().await.context("Failed to read a line")? {
+            line_no += 1;
+            let line = line.trim();
+            if line.is_empty() || format.is_header(line) {
+                continue;
+            }
+
+            let pending = match format.decode(line) {
+                Ok(record) => Pending::Submitted(ingest.enqueue(record).await?),
+                Err(err) => Pending::Malformed(format!("{err:#}")),
+            };
+            if pending_tx.send((line_no, pending)).await.is_err() {
+                // The writer stopped, e.g. because the producer closed the connection
+                break;
+            }
+        }
+
+        Ok::<_, anyhow::Error>(())
+    };
+
+    let write = async move {
+        let mut writer = BufWriter::new(writer);
+
+        while let Some((line, pending)) = pending_rx.recv().await {
+            let outcome = match pending {
+                Pending::Submitted(outcome) => AckOutcome::Record(
+                    outcome
+                        .await
+                        .context("Failed to receive the outcome, because the engine stopped")?,
+                ),
+                Pending::Malformed(message) => AckOutcome::Malformed(Malformed { message }),
+            };
+
+            let mut ack = serde_json::to_vec(&Ack { line, outcome })
+                .context("Failed to serialize the ack")?;
+            ack.push(b'\n');
+            writer
+                .write_all(&ack)
+                .await
+                .context("Failed to write the ack")?;
+
+            // Flushing only, when no further ack is ready, batches the writes under load
+            if pending_rx.is_empty() {
+                writer.flush().await.context("Failed to flush the acks")?;
+            }
+        }
+
+        writer.flush().await.context("Failed to flush the acks")?;
+        writer
+            .shutdown()
+            .await
+            .context("Failed to shut down the connection")
+    };
+
+    let (read, write) = tokio::join!(read, write);
+    read.and(write)
+}
+
+/// Accepts producers on the TCP listener until it fails. Every connection is served by its own task.
+pub async fn serve_tcp(listener: TcpListener, ingest: Ingest, format: LineFormat) -> Result<()> {
+    loop {
+        let (stream, peer) = listener
+            .accept()
+            .await
+            .context("Failed to accept a TCP connection")?;
+        let ingest = ingest.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(stream, ingest, format).await {
+                warn!("Failed to serve the connection of {peer}: {err:?}");
+            }
+        });
+    }
+}
+
+/// Accepts producers on the Unix domain socket until it fails. Every connection is served by its own task.
+#[cfg(unix)]
+pub async fn serve_unix(listener: UnixListener, ingest: Ingest, format: LineFormat) -> Result<()> {
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .context("Failed to accept a Unix socket connection")?;
+        let ingest = ingest.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(stream, ingest, format).await {
+                warn!("Failed to serve a Unix socket connection: {err:?}");
+            }
+        });
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::csv::DecodeStats;
+use crate::models::rejection::Rejection;
+use crate::models::transaction::TxRecord;
+
+/// Collects the outcomes of the records of a run, see [crate::prelude::PaymentEngineBuilder::run_stats].
+///
+/// The clock and the number of existing accounts are captured, when the engine starts processing a stream. Thus the summary only covers the run, even if the repositories weren't empty.
+#[derive(Debug, Default)]
+pub struct RunStats {
+    started: OnceLock<Start>,
+    tally: Mutex<Tally>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Start {
+    at: Instant,
+    accounts: usize,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    applied: u64,
+    rejected: u64,
+    failed: u64,
+    rejections: BTreeMap<&'static str, u64>,
+    types: BTreeMap<&'static str, TypeSummary>,
+    sink_failures: BTreeMap<&'static str, u64>,
+    aborted: Option<Abort>,
+}
+
+/// The summary of a run, e.g. for sanity checking a nightly file. It is printed to stderr or written as JSON by the CLI.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub records: RecordCounts,
+    /// The rejected records by the reason of the [Rejection]
+    pub rejections: BTreeMap<&'static str, u64>,
+    /// The decoded records by type, as in the `type` column of the CSV input
+    pub types: BTreeMap<&'static str, TypeSummary>,
+    /// The records, that couldn't be written to a sink, by sink, e.g. `journal` or `audit_log`. The failing sink doesn't change the outcome of the record.
+    pub sink_failures: BTreeMap<&'static str, u64>,
+    pub accounts_created: usize,
+    /// The locked accounts after the run, including the ones locked before
+    pub accounts_locked: usize,
+    /// The disputed transactions after the run, that are neither resolved nor charged back
+    pub open_disputes: usize,
+    pub elapsed_secs: f64,
+    /// Decoded records per second
+    pub throughput: f64,
+    /// The record, that aborted the run in strict mode. The counts only cover the records up to it.
+    pub aborted: Option<Abort>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Abort {
+    /// The line of the record in the input, if it is known
+    pub line: Option<u64>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RecordCounts {
+    /// The rows of the input, including the malformed ones
+    pub read: u64,
+    pub decoded: u64,
+    pub applied: u64,
+    pub rejected: u64,
+    /// Unexpected errors, e.g. of a repository
+    pub failed: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TypeSummary {
+    pub count: u64,
+    pub applied: u64,
+    /// The sum of the applied amounts. Only deposits and withdrawals carry an amount.
+    pub amount: Decimal,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tally> {
+        // The tally only holds counters, thus a poisoned one is still good enough for a summary
+        self.tally.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Only the first call counts, thus processing several streams extends the run
+    pub(crate) fn start(&self, accounts: usize) {
+        self.started.get_or_init(|| Start {
+            at: Instant::now(),
+            accounts,
+        });
+    }
+
+    pub(crate) fn observe_record(&self, record: &TxRecord, res: &Result<()>) {
+        let mut tally = self.lock();
+
+        let applied = res.is_ok();
+        match res {
+            Ok(()) => tally.applied += 1,
+            Err(err) => match Rejection::of(err) {
+                Some(rejection) => {
+                    tally.rejected += 1;
+                    *tally.rejections.entry(rejection.reason()).or_default() += 1;
+                }
+                None => tally.failed += 1,
+            },
+        }
+
+        let summary = tally.types.entry(record.type_name()).or_default();
+        summary.count += 1;
+        if applied {
+            summary.applied += 1;
+            let amount = match record {
+                TxRecord::Deposit(deposit) => deposit.amount.into_inner(),
+                TxRecord::Withdrawal(withdrawal) => withdrawal.amount.into_inner(),
+                _ => Decimal::ZERO,
+            };
+            summary.amount = summary.amount.saturating_add(amount);
+        }
+    }
+
+    pub(crate) fn observe_sink_failure(&self, sink: &'static str) {
+        let mut tally = self.lock();
+        *tally.sink_failures.entry(sink).or_default() += 1;
+    }
+
+    /// Only the first failing record aborts a run in strict mode
+    pub(crate) fn abort(&self, line: Option<u64>, err: &anyhow::Error) {
+        let mut tally = self.lock();
+        tally.aborted.get_or_insert_with(|| Abort {
+            line,
+            error: format!("{err:#}"),
+        });
+    }
+
+    /// Summarizes the run up to now, given the counts of the decoder and the current state of the repositories
+    pub(crate) fn summarize(
+        &self,
+        decoding: DecodeStats,
+        accounts: usize,
+        accounts_locked: usize,
+        open_disputes: usize,
+    ) -> RunSummary {
+        let tally = self.lock();
+        let (elapsed, accounts_before) = match self.started.get() {
+            Some(start) => (start.at.elapsed(), start.accounts),
+            None => (Duration::ZERO, accounts),
+        };
+        let elapsed_secs = elapsed.as_secs_f64();
+        let throughput = match elapsed_secs > 0.0 {
+            true => decoding.decoded as f64 / elapsed_secs,
+            false => 0.0,
+        };
+
+        RunSummary {
+            records: RecordCounts {
+                read: decoding.read,
+                decoded: decoding.decoded,
+                applied: tally.applied,
+                rejected: tally.rejected,
+                failed: tally.failed,
+            },
+            rejections: tally.rejections.clone(),
+            types: tally.types.clone(),
+            sink_failures: tally.sink_failures.clone(),
+            accounts_created: accounts.saturating_sub(accounts_before),
+            accounts_locked,
+            open_disputes,
+            elapsed_secs,
+            throughput,
+            aborted: tally.aborted.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use rust_decimal::dec;
+
+    use super::*;
+    use crate::models::NonNegativeDecimal;
+    use crate::models::client::ClientId;
+    use crate::models::transaction::{Deposit, TransactionId};
+
+    fn deposit(amount: u32) -> TxRecord {
+        TxRecord::Deposit(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(amount).unwrap(),
+        })
+    }
+
+    #[test]
+    fn only_applied_amounts_are_summed() {
+        let stats = RunStats::new();
+        stats.start(2);
+        let rejected = Err(anyhow!(Rejection::UnknownAccount {
+            client: ClientId::new(1)
+        }));
+
+        stats.observe_record(&deposit(10), &Ok(()));
+        stats.observe_record(&deposit(5), &rejected);
+        stats.observe_record(&deposit(7), &Err(anyhow!("boom")));
+
+        let decoding = DecodeStats {
+            read: 4,
+            decoded: 3,
+        };
+        let summary = stats.summarize(decoding, 3, 0, 0);
+        assert_eq!(
+            summary.records,
+            RecordCounts {
+                read: 4,
+                decoded: 3,
+                applied: 1,
+                rejected: 1,
+                failed: 1,
+            }
+        );
+        assert_eq!(summary.rejections["unknown_account"], 1);
+        assert_eq!(
+            summary.types["deposit"],
+            TypeSummary {
+                count: 3,
+                applied: 1,
+                amount: dec!(10),
+            }
+        );
+        assert_eq!(summary.accounts_created, 1);
+    }
+
+    #[test]
+    fn applied_amounts_saturate_instead_of_overflowing() {
+        let stats = RunStats::new();
+        let deposit = TxRecord::Deposit(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(Decimal::MAX).unwrap(),
+        });
+
+        stats.observe_record(&deposit, &Ok(()));
+        stats.observe_record(&deposit, &Ok(()));
+
+        let summary = stats.summarize(DecodeStats::default(), 1, 0, 0);
+        assert_eq!(summary.types["deposit"].amount, Decimal::MAX);
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{self, IsTerminal};
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt};
+
+const SERVICE_NAME: &str = "toy-payment-engine";
+
+/// The `record` span carries the client, the tx, the type and the line of a record. Thus it is enabled by default, otherwise the logged failures would lose their context.
+const RECORD_SPAN: &str = "toy_payment_engine[record]=info";
+
+/// The output of the log events and spans on stderr. stdout is reserved for the results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable, spread over several lines together with the spans of the event
+    #[default]
+    Pretty,
+    /// One JSON object per event, including the spans of the event
+    Json,
+    Off,
+}
+
+/// The installed subscriber. It must be shut down at the end, to export the pending spans.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Installs the global subscriber. The logs are filtered by `RUST_LOG` (e.g. `RUST_LOG=toy_payment_engine=debug`), which defaults to `warn` within the `record` span of the failing record.
+    /// The logs are only colored, if stderr is a terminal.
+    ///
+    /// With an OTLP endpoint, e.g. `http://127.0.0.1:4317` of a local collector, the spans are exported via gRPC in batches as well. This also works with [LogFormat::Off].
+    /// The export has its own filter, that defaults to `info`, thus the `record` spans are exported without setting `RUST_LOG`. An explicit `RUST_LOG` applies to both.
+    pub fn init(format: LogFormat, otlp_endpoint: Option<&str>) -> Result<Self> {
+        let provider = otlp_endpoint.map(tracer_provider).transpose()?;
+        let rust_log = std::env::var("RUST_LOG").ok();
+
+        tracing_subscriber::registry()
+            .with(layers(
+                format,
+                io::stderr,
+                io::stderr().is_terminal(),
+                provider.as_ref(),
+                rust_log.as_deref(),
+            )?)
+            .try_init()
+            .context("Failed to install the tracing subscriber")?;
+
+        Ok(Self { provider })
+    }
+
+    /// Flushes the pending spans to the collector
+    pub fn shutdown(self) -> Result<()> {
+        if let Some(provider) = self.provider {
+            provider
+                .shutdown()
+                .context("Failed to shut down the OpenTelemetry export")?;
+        }
+
+        Ok(())
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Every layer has its own filter, thus the default level of the logs doesn't restrict the exported spans
+fn layers<W>(
+    format: LogFormat,
+    writer: W,
+    ansi: bool,
+    provider: Option<&SdkTracerProvider>,
+    rust_log: Option<&str>,
+) -> Result<Vec<BoxedLayer>>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+    match format {
+        LogFormat::Pretty => layers.push(
+            fmt::layer()
+                .pretty()
+                .with_ansi(ansi)
+                .with_writer(writer)
+                .with_filter(log_filter(rust_log)?)
+                .boxed(),
+        ),
+        LogFormat::Json => layers.push(
+            fmt::layer()
+                .json()
+                .with_ansi(ansi)
+                .with_writer(writer)
+                .with_filter(log_filter(rust_log)?)
+                .boxed(),
+        ),
+        LogFormat::Off => {}
+    }
+
+    if let Some(provider) = provider {
+        layers.push(
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(SERVICE_NAME))
+                .with_filter(env_filter(rust_log, LevelFilter::INFO)?)
+                .boxed(),
+        );
+    }
+
+    Ok(layers)
+}
+
+/// The filter of the logs. Without `RUST_LOG`, the `record` span is enabled on top of `warn`
+fn log_filter(rust_log: Option<&str>) -> Result<EnvFilter> {
+    let filter = env_filter(rust_log, LevelFilter::WARN)?;
+    if rust_log.is_some() {
+        return Ok(filter);
+    }
+
+    let record_span: Directive = RECORD_SPAN
+        .parse()
+        .context("Failed to parse the directive of the record span")?;
+
+    Ok(filter.add_directive(record_span))
+}
+
+/// The filter of `RUST_LOG`, or the given level, if it isn't set
+fn env_filter(rust_log: Option<&str>, default: LevelFilter) -> Result<EnvFilter> {
+    EnvFilter::builder()
+        .with_default_directive(default.into())
+        .parse(rust_log.unwrap_or_default())
+        .context("Failed to parse the filter of RUST_LOG")
+}
+
+fn tracer_provider(endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .with_context(|| format!("Failed to create the OTLP exporter for: {endpoint}"))?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use claims::assert_ok;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+    use tracing::{debug_span, error, info_span};
+
+    use super::*;
+
+    /// Collects the log lines in memory
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Capture {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Collects the names of the exported spans
+    #[derive(Debug, Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<String>>>);
+
+    impl SpanExporter for Collector {
+        async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+            let mut names = self.0.lock().unwrap();
+            names.extend(batch.into_iter().map(|span| span.name.into_owned()));
+            Ok(())
+        }
+    }
+
+    fn exported_spans(rust_log: Option<&str>) -> Vec<String> {
+        let collector = Collector::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(collector.clone())
+            .build();
+        let layers = assert_ok!(layers(
+            LogFormat::Json,
+            io::sink,
+            false,
+            Some(&provider),
+            rust_log
+        ));
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layers), || {
+            info_span!("record").in_scope(|| debug_span!("repository").in_scope(|| {}));
+        });
+
+        collector.0.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn record_spans_are_exported_by_default() {
+        assert_eq!(exported_spans(None), ["record"]);
+    }
+
+    #[test]
+    fn rust_log_applies_to_the_export() {
+        assert_eq!(exported_spans(Some("debug")), ["repository", "record"]);
+    }
+
+    #[test]
+    fn failures_are_logged_within_the_record_span_by_default() {
+        let capture = Capture::default();
+        let layers = assert_ok!(layers(
+            LogFormat::Pretty,
+            capture.clone(),
+            false,
+            None,
+            None
+        ));
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layers), || {
+            info_span!("record", client = 7).in_scope(|| error!("Failed to process TxRecord"));
+        });
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs.contains("in toy_payment_engine::telemetry::tests::record with client: 7"),
+            "Missing the record span in:\n{logs}"
+        );
+        assert!(!logs.contains('\x1b'), "Unexpected ANSI escape in:\n{logs}");
+    }
+}
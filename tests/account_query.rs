@@ -0,0 +1,143 @@
+use claims::{assert_none, assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{AccountQuery, AccountRepository, AccountTotals};
+
+mod setup;
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+/// Disputes, resolves and charges back the transaction, which locks the account of the client
+fn charge_back(client: u16, tx: u32) -> [TxRecord; 3] {
+    let client_id = ClientId::new(client);
+    let tx_id = TransactionId::new(tx);
+
+    [
+        TxRecord::from(Dispute { client_id, tx_id }),
+        TxRecord::from(Resolve { client_id, tx_id }),
+        TxRecord::from(Chargeback { client_id, tx_id }),
+    ]
+}
+
+fn client_ids(accounts: &[Account]) -> Vec<ClientId> {
+    accounts.iter().map(|acc| acc.client_id).collect()
+}
+
+#[tokio::test]
+async fn query_locked_accounts_and_accounts_with_held_funds() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(1, 1, 10),
+        TxRecord::from(Dispute {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+        }),
+        deposit(2, 2, 5),
+        deposit(3, 3, 100),
+    ]
+    .into_iter()
+    .chain(charge_back(2, 2));
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let locked = assert_ok!(accounts.query(&AccountQuery::new().locked()).await);
+    let with_held = assert_ok!(accounts.query(&AccountQuery::new().with_held()).await);
+    let locked_with_held = assert_ok!(
+        accounts
+            .count(&AccountQuery::new().locked().with_held())
+            .await
+    );
+
+    // assert
+    assert_eq!(client_ids(&locked.items), [ClientId::new(2)]);
+    assert_eq!(client_ids(&with_held.items), [ClientId::new(1)]);
+    assert_eq!(locked_with_held, 0, "unexpected count");
+}
+
+#[tokio::test]
+async fn query_accounts_with_total_below_threshold_in_client_order() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(4, 1, 1),
+        deposit(3, 2, 100),
+        deposit(2, 3, 5),
+        deposit(1, 4, 20),
+    ];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+    let query = AccountQuery::new().total_below(dec!(25)).limit(2);
+
+    // act
+    let first = assert_ok!(accounts.query(&query).await);
+    let cursor = assert_some!(first.next, "Expected a second page");
+    let second = assert_ok!(accounts.query(&query.after(cursor)).await);
+
+    // assert
+    assert_eq!(
+        client_ids(&first.items),
+        [ClientId::new(1), ClientId::new(2)]
+    );
+    assert_eq!(client_ids(&second.items), [ClientId::new(4)]);
+    assert_none!(second.next, "Expected no third page");
+    assert_eq!(assert_ok!(accounts.count(&query).await), 3);
+}
+
+#[tokio::test]
+async fn totals_are_aggregated_across_all_clients() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(1, 1, 10),
+        deposit(1, 2, 10),
+        TxRecord::from(Dispute {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+        }),
+        deposit(2, 3, 5),
+        deposit(3, 4, 100),
+        deposit(4, 5, 1),
+    ]
+    .into_iter()
+    .chain(charge_back(2, 3));
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let totals = assert_ok!(accounts.totals().await);
+
+    // assert
+    assert_eq!(
+        totals,
+        AccountTotals {
+            accounts: 4,
+            locked: 1,
+            available: dec!(111),
+            held: dec!(10),
+            total: dec!(121),
+        }
+    );
+}
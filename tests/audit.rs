@@ -0,0 +1,141 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use claims::{assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Deposit, Dispute, TransactionId, TxRecord, Withdrawal,
+};
+use toy_payment_engine::prelude::{
+    AccountRepository, AuditLog, AuditSink, InMemoryAccountRepository, InMemoryLedger,
+    InMemoryTxRepository, LedgerRepository, PaymentEngine, RunStats, Verification, verify,
+};
+
+/// Processes the records with an audit log and returns its content
+async fn audit_log_of(records: Vec<TxRecord>) -> String {
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::new());
+    let audit_log = Arc::new(AuditLog::new(Vec::new()));
+    let engine = PaymentEngine::builder(accounts, transactions)
+        .audit_log(Arc::clone(&audit_log) as Arc<dyn AuditSink>)
+        .build();
+
+    assert_ok!(engine.process(stream::iter(records).fuse()).await);
+    assert_ok!(audit_log.flush());
+    drop(engine);
+
+    let audit_log = Arc::into_inner(audit_log).expect("The engine has been dropped");
+    String::from_utf8(audit_log.into_inner()).unwrap()
+}
+
+#[tokio::test]
+async fn only_applied_records_are_chained() {
+    // arrange
+    let client_id = ClientId::new(1);
+    let records = vec![
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        // rejected, thus not part of the audit log
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(20).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id,
+            tx_id: TransactionId::new(1),
+        }),
+    ];
+
+    // act
+    let log = audit_log_of(records).await;
+
+    // assert
+    assert_eq!(log.lines().count(), 2, "unexpected number of entries");
+    assert!(log.lines().nth(1).unwrap().contains(r#""type":"dispute""#));
+
+    let verification = assert_ok!(verify(Cursor::new(log)));
+    assert_eq!(verification, Verification::Intact { entries: 2 });
+}
+
+#[tokio::test]
+async fn reordered_entries_are_reported_as_broken_link() {
+    // arrange
+    let records = [1, 2]
+        .map(|tx| {
+            TxRecord::from(Deposit {
+                client_id: ClientId::new(1),
+                tx_id: TransactionId::new(tx),
+                amount: NonNegativeDecimal::try_from(10).unwrap(),
+            })
+        })
+        .to_vec();
+    let log = audit_log_of(records).await;
+    let lines: Vec<_> = log.lines().rev().collect();
+
+    // act
+    let verification = assert_ok!(verify(Cursor::new(lines.join("\n"))));
+
+    // assert
+    assert_eq!(
+        verification,
+        Verification::Broken {
+            line: 1,
+            reason: "expected seq 1, but found 2".to_owned()
+        }
+    );
+}
+
+/// Fails every append, e.g. like an audit log on a full disk
+struct FullDisk;
+
+impl AuditSink for FullDisk {
+    fn append(&self, _record: TxRecord, _account: Option<Account>) -> Result<()> {
+        bail!("No space left on device")
+    }
+
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn failing_audit_log_does_not_fail_the_applied_record() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let ledger = Arc::new(InMemoryLedger::new());
+    let engine =
+        PaymentEngine::builder(Arc::clone(&accounts), Arc::new(InMemoryTxRepository::new()))
+            .audit_log(Arc::new(FullDisk))
+            .ledger(Arc::clone(&ledger) as Arc<dyn LedgerRepository>)
+            .run_stats(Arc::new(RunStats::new()))
+            .build();
+
+    let deposit = TxRecord::from(Deposit {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    });
+
+    // act
+    let res = engine.process_record(deposit).await;
+
+    // assert
+    assert_ok!(res);
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.available, dec!(10));
+    // The remaining sinks are still written
+    assert_eq!(ledger.entries(None).count().await, 1);
+    let summary = assert_some!(assert_ok!(engine.run_summary(Default::default()).await));
+    assert_eq!(summary.records.applied, 1);
+    assert_eq!(summary.sink_failures["audit_log"], 1);
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use claims::{assert_err, assert_ok, assert_some, assert_some_eq};
+use rust_decimal::dec;
+use serde_json::{Value, json};
+
+use setup::Components;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{TransactionId, TxRecord};
+use toy_payment_engine::prelude::{
+    AccountRepository, Decision, EngineConfig, FailureMode, InMemoryAccountRepository,
+    InMemoryBroker, InMemoryTxRepository, LineFormat, OutcomePublisher, PartitionConsumer,
+    PaymentEngine, PolicyContext, Producer, RunStats, TxPolicy, process_partition,
+};
+
+mod setup;
+
+async fn produce(broker: &InMemoryBroker, lines: &[&str]) {
+    let producer = broker.producer("transactions", 0);
+    for line in lines {
+        assert_ok!(producer.send(None, line.as_bytes().to_vec()).await);
+    }
+}
+
+#[tokio::test]
+async fn partition_is_processed_and_committed() {
+    // arrange
+    let broker = InMemoryBroker::new();
+    produce(
+        &broker,
+        &[
+            "deposit,1,1,10",
+            "not a record",
+            "withdrawal,1,2,4",
+            "withdrawal,1,3,100",
+        ],
+    )
+    .await;
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+    let consumer = broker.consumer("engine", "transactions", 0).await;
+
+    // act
+    assert_ok!(process_partition(&engine, consumer, LineFormat::Csv).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.available, dec!(6));
+    assert_some_eq!(broker.committed("engine", "transactions", 0).await, 4);
+}
+
+/// Fails every record with the given tx id, like an unavailable repository
+struct FailingTx(u32);
+
+#[async_trait]
+impl TxPolicy for FailingTx {
+    fn name(&self) -> &'static str {
+        "failing_tx"
+    }
+
+    async fn evaluate(&self, record: &TxRecord, _context: &PolicyContext<'_>) -> Result<Decision> {
+        match record.tx_id() == TransactionId::new(self.0) {
+            true => bail!("The repository is unavailable"),
+            false => Ok(Decision::Allow),
+        }
+    }
+}
+
+#[tokio::test]
+async fn failed_record_stays_uncommitted_in_lenient_mode() {
+    // arrange
+    let broker = InMemoryBroker::new();
+    produce(
+        &broker,
+        &[
+            r#"{"type":"deposit","client":1,"tx":1,"amount":"10"}"#,
+            r#"{"type":"deposit","client":1,"tx":2,"amount":"5"}"#,
+            r#"{"type":"deposit","client":1,"tx":3,"amount":"5"}"#,
+        ],
+    )
+    .await;
+    let engine = PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    )
+    .policy(Arc::new(FailingTx(2)))
+    .build();
+    assert_eq!(engine.config().failure_mode, FailureMode::Lenient);
+    let consumer = broker.consumer("engine", "transactions", 0).await;
+
+    // act
+    assert_err!(process_partition(&engine, consumer, LineFormat::Json).await);
+
+    // assert
+    assert_some_eq!(broker.committed("engine", "transactions", 0).await, 1);
+    let mut restarted = broker.consumer("engine", "transactions", 0).await;
+    let redelivered = assert_some!(assert_ok!(restarted.poll().await));
+    assert_eq!(redelivered.offset, 1);
+}
+
+#[tokio::test]
+async fn rejected_record_stays_uncommitted_in_strict_mode() {
+    // arrange
+    let broker = InMemoryBroker::new();
+    produce(
+        &broker,
+        &["deposit,1,1,10", "withdrawal,1,2,100", "deposit,1,3,5"],
+    )
+    .await;
+    let engine = PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    )
+    .config(EngineConfig {
+        failure_mode: FailureMode::Strict,
+        ..EngineConfig::default()
+    })
+    .run_stats(Arc::new(RunStats::new()))
+    .build();
+    let consumer = broker.consumer("engine", "transactions", 0).await;
+
+    // act
+    assert_err!(process_partition(&engine, consumer, LineFormat::Csv).await);
+
+    // assert
+    assert_some_eq!(broker.committed("engine", "transactions", 0).await, 1);
+    let summary = assert_some!(assert_ok!(engine.run_summary(Default::default()).await));
+    assert_eq!(summary.records.applied, 1);
+    assert_eq!(summary.records.rejected, 1);
+    let aborted = assert_some!(summary.aborted);
+    assert!(
+        aborted.error.contains("offset: 1"),
+        "unexpected error: {}",
+        aborted.error
+    );
+}
+
+#[tokio::test]
+async fn outcomes_are_published_keyed_by_client() {
+    // arrange
+    let broker = InMemoryBroker::new();
+    let publisher = OutcomePublisher::new(broker.producer("outcomes", 0));
+    let Components { engine, .. } = Components::setup();
+    let records = [
+        assert_ok!(serde_json::from_value(
+            json!({"type": "deposit", "client": 7, "tx": 1, "amount": "10"})
+        )),
+        assert_ok!(serde_json::from_value(
+            json!({"type": "withdrawal", "client": 7, "tx": 2, "amount": "20"})
+        )),
+    ];
+
+    // act
+    for record in records {
+        let outcome = engine.submit(record).await;
+        assert_ok!(publisher.publish(&record, &outcome).await);
+    }
+
+    // assert
+    let messages = broker.messages("outcomes", 0).await;
+    assert_eq!(messages.len(), 2);
+    assert_some_eq!(messages[0].key.as_deref(), b"7".as_slice());
+
+    let applied: Value = assert_ok!(serde_json::from_slice(&messages[0].payload));
+    assert_eq!(applied["outcome"], "applied");
+    assert_eq!(applied["record"]["tx"], 1);
+    assert_eq!(applied["account"]["available"], "10");
+
+    let rejected: Value = assert_ok!(serde_json::from_slice(&messages[1].payload));
+    assert_eq!(rejected["outcome"], "rejected");
+    assert_eq!(rejected["rejection"]["reason"], "insufficient_funds");
+}
@@ -0,0 +1,324 @@
+use claims::{assert_err, assert_none, assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TransactionStatus, TxRecord, Withdrawal,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{
+    AccountRepository, DisputePolicy, EngineConfig, FailureMode, LockedAccountPolicy,
+    TransactionRepository,
+};
+
+mod setup;
+
+/// Deposits 10 and 5, disputes and resolves the 10 and charges it back, which locks the account with 5 available.
+fn locked_account_records(client_id: ClientId) -> Vec<TxRecord> {
+    let tx_id = TransactionId::new(1);
+
+    vec![
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        }),
+        TxRecord::from(Dispute { client_id, tx_id }),
+        TxRecord::from(Resolve { client_id, tx_id }),
+        TxRecord::from(Chargeback { client_id, tx_id }),
+    ]
+}
+
+#[tokio::test]
+async fn withdrawal_does_not_open_an_account_by_default() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let txs = [TxRecord::from(Withdrawal {
+        client_id,
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(1).unwrap(),
+    })];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_ok!(accounts.get(client_id).await);
+    assert_none!(account, "No account should have been opened");
+}
+
+#[tokio::test]
+async fn withdrawal_opens_an_account_if_configured() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::with_config(EngineConfig {
+        withdrawal_opens_account: true,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(1);
+    let txs = [TxRecord::from(Withdrawal {
+        client_id,
+        tx_id,
+        amount: NonNegativeDecimal::try_from(1).unwrap(),
+    })];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_ok!(accounts.get(client_id).await);
+    let account = assert_some!(account, "An empty account should have been opened");
+    assert_eq!(account.available, dec!(0), "unexpected available amount");
+    assert_eq!(account.total, dec!(0), "unexpected total amount");
+
+    let tx = assert_some!(transactions.get(tx_id).await);
+    assert_eq!(tx.status, TransactionStatus::Failed, "Unexpected tx_status");
+}
+
+#[tokio::test]
+async fn locked_account_is_ignored_by_default() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let mut txs = locked_account_records(client_id);
+    txs.push(TxRecord::from(Withdrawal {
+        client_id,
+        tx_id: TransactionId::new(3),
+        amount: NonNegativeDecimal::try_from(2).unwrap(),
+    }));
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert!(account.is_locked, "unexpected is_locked");
+    assert_eq!(account.available, dec!(3), "unexpected available amount");
+}
+
+#[tokio::test]
+async fn locked_account_blocks_withdrawals_if_configured() {
+    let Components {
+        engine, accounts, ..
+    } = Components::with_config(EngineConfig {
+        locked_account: LockedAccountPolicy::BlockWithdrawals,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let mut txs = locked_account_records(client_id);
+    txs.push(TxRecord::from(Withdrawal {
+        client_id,
+        tx_id: TransactionId::new(3),
+        amount: NonNegativeDecimal::try_from(2).unwrap(),
+    }));
+    txs.push(TxRecord::from(Deposit {
+        client_id,
+        tx_id: TransactionId::new(4),
+        amount: NonNegativeDecimal::try_from(1).unwrap(),
+    }));
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert!(account.is_locked, "unexpected is_locked");
+    assert_eq!(account.available, dec!(6), "unexpected available amount");
+}
+
+#[tokio::test]
+async fn locked_account_is_frozen_if_configured() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::with_config(EngineConfig {
+        locked_account: LockedAccountPolicy::Freeze,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let frozen_tx_id = TransactionId::new(2);
+    let mut txs = locked_account_records(client_id);
+    txs.push(TxRecord::from(Deposit {
+        client_id,
+        tx_id: TransactionId::new(3),
+        amount: NonNegativeDecimal::try_from(1).unwrap(),
+    }));
+    txs.push(TxRecord::from(Dispute {
+        client_id,
+        tx_id: frozen_tx_id,
+    }));
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(5), "unexpected available amount");
+    assert_eq!(account.held, dec!(0), "unexpected held amount");
+
+    let tx = assert_some!(transactions.get(frozen_tx_id).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Processed,
+        "Unexpected tx_status"
+    );
+}
+
+#[tokio::test]
+async fn dispute_of_withdrawal_is_rejected_if_only_deposits_can_be_disputed() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::with_config(EngineConfig {
+        dispute: DisputePolicy::DepositsOnly,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let withdrawal_tx_id = TransactionId::new(2);
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: withdrawal_tx_id,
+            amount: NonNegativeDecimal::try_from(4).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id,
+            tx_id: withdrawal_tx_id,
+        }),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.held, dec!(0), "unexpected held amount");
+    assert_eq!(account.total, dec!(6), "unexpected total amount");
+
+    let tx = assert_some!(transactions.get(withdrawal_tx_id).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Processed,
+        "Unexpected tx_status"
+    );
+}
+
+#[tokio::test]
+async fn amounts_exceeding_precision_or_max_amount_are_rejected() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::with_config(EngineConfig {
+        precision: Some(4),
+        max_amount: Some(dec!(100)),
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let too_precise = TransactionId::new(2);
+    let too_large = TransactionId::new(3);
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(dec!(1.2340)).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: too_precise,
+            amount: NonNegativeDecimal::try_from(dec!(1.23456)).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: too_large,
+            amount: NonNegativeDecimal::try_from(dec!(100.01)).unwrap(),
+        }),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(
+        account.available,
+        dec!(1.234),
+        "unexpected available amount"
+    );
+    assert_none!(transactions.get(too_precise).await);
+    assert_none!(transactions.get(too_large).await);
+}
+
+#[tokio::test]
+async fn strict_mode_stops_at_the_first_failing_record() {
+    let Components {
+        engine, accounts, ..
+    } = Components::with_config(EngineConfig {
+        failure_mode: FailureMode::Strict,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(20).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+    ];
+
+    // act
+    let res = engine.process(stream::iter(txs).fuse()).await;
+
+    // assert
+    assert_err!(res, "Expected the processing to fail in strict mode");
+
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(10), "unexpected available amount");
+}
@@ -34,7 +34,7 @@ async fn can_perform_deposit_for_new_client() {
     .map(TxRecord::from);
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -79,7 +79,7 @@ async fn can_perform_multiple_deposits() {
         .map(TxRecord::from);
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
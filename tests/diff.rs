@@ -0,0 +1,105 @@
+use claims::{assert_err, assert_ok};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{Deposit, TransactionId, TxRecord, Withdrawal};
+use toy_payment_engine::prelude::{
+    AccountField, DiscrepancyKind, EngineConfig, FailureMode, diff_engines,
+};
+
+use setup::Components;
+
+mod setup;
+
+#[tokio::test]
+async fn diverging_accounts_are_reported() {
+    // arrange
+    let baseline = Components::setup();
+    let candidate = Components::with_config(EngineConfig {
+        max_amount: Some(dec!(50)),
+        withdrawal_opens_account: true,
+        ..EngineConfig::default()
+    });
+
+    let records = [
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(100).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        // above the max amount of the candidate
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(4),
+            amount: NonNegativeDecimal::try_from(60).unwrap(),
+        }),
+        // opens an empty account in the candidate only
+        TxRecord::from(Withdrawal {
+            client_id: ClientId::new(3),
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(1).unwrap(),
+        }),
+    ];
+
+    // act
+    let diff = assert_ok!(diff_engines(&baseline.engine, &candidate.engine, &records).await);
+
+    // assert
+    let findings: Vec<_> = diff
+        .reconciliation
+        .discrepancies
+        .iter()
+        .map(|discrepancy| (discrepancy.client, discrepancy.kind, discrepancy.field))
+        .collect();
+    assert_eq!(
+        findings,
+        [
+            (ClientId::new(1), DiscrepancyKind::Missing, None),
+            (
+                ClientId::new(2),
+                DiscrepancyKind::Mismatch,
+                Some(AccountField::Available)
+            ),
+            (
+                ClientId::new(2),
+                DiscrepancyKind::Mismatch,
+                Some(AccountField::Total)
+            ),
+            (ClientId::new(3), DiscrepancyKind::Unexpected, None),
+        ]
+    );
+    assert_eq!(diff.summary.clients, 3);
+    assert_eq!(diff.summary.changed, 1);
+    assert_eq!(diff.summary.baseline_total, dec!(170));
+    assert_eq!(diff.summary.candidate_total, dec!(10));
+}
+
+#[tokio::test]
+async fn failing_strict_engine_is_an_error() {
+    // arrange
+    let baseline = Components::setup();
+    let candidate = Components::with_config(EngineConfig {
+        max_amount: Some(dec!(50)),
+        failure_mode: FailureMode::Strict,
+        ..EngineConfig::default()
+    });
+
+    // above the max amount of the candidate
+    let records = [TxRecord::from(Deposit {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(60).unwrap(),
+    })];
+
+    // act
+    let res = diff_engines(&baseline.engine, &candidate.engine, &records).await;
+
+    // assert
+    assert_err!(res);
+}
@@ -36,7 +36,7 @@ async fn can_perform_dispute_for_complete_available_amount() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -103,7 +103,7 @@ async fn can_perform_dispute_with_multiple_deposits() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -175,7 +175,7 @@ async fn cant_perform_dispute_if_more_than_available() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -245,7 +245,7 @@ async fn can_perform_dispute_of_withdrawal() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use claims::{assert_err, assert_ok};
+use rust_decimal::dec;
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, broadcast};
+
+use setup::Components;
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+};
+use toy_payment_engine::prelude::{AccountEvent, Webhook};
+
+mod setup;
+
+fn deposit(tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn withdrawal(tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Withdrawal(Withdrawal {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn drain(events: &mut broadcast::Receiver<AccountEvent>) -> Vec<AccountEvent> {
+    std::iter::from_fn(|| events.try_recv().ok()).collect()
+}
+
+#[tokio::test]
+async fn applied_records_publish_their_events() {
+    // arrange
+    let Components { engine, .. } = Components::setup();
+    let mut events = engine.subscribe();
+    let tx_id = TransactionId::new(1);
+
+    // act
+    assert_ok!(engine.process_record(deposit(1, 10)).await);
+    assert_ok!(
+        engine
+            .process_record(TxRecord::Dispute(Dispute {
+                client_id: ClientId::new(1),
+                tx_id,
+            }))
+            .await
+    );
+    assert_ok!(
+        engine
+            .process_record(TxRecord::Resolve(Resolve {
+                client_id: ClientId::new(1),
+                tx_id,
+            }))
+            .await
+    );
+    assert_ok!(
+        engine
+            .process_record(TxRecord::Chargeback(Chargeback {
+                client_id: ClientId::new(1),
+                tx_id,
+            }))
+            .await
+    );
+
+    // assert
+    let events = drain(&mut events);
+    assert!(matches!(
+        events.as_slice(),
+        [
+            AccountEvent::BalanceChanged { .. },
+            AccountEvent::DisputeOpened { .. },
+            AccountEvent::DisputeResolved { .. },
+            AccountEvent::ChargedBack { .. },
+            AccountEvent::AccountLocked { .. },
+        ]
+    ));
+    let locked = events[4].account();
+    assert!(locked.is_locked);
+    assert_eq!(locked.total, dec!(0));
+}
+
+#[tokio::test]
+async fn rejected_record_publishes_nothing() {
+    // arrange
+    let Components { engine, .. } = Components::setup();
+    assert_ok!(engine.process_record(deposit(1, 10)).await);
+    let mut events = engine.subscribe();
+
+    // act
+    assert_err!(engine.process_record(withdrawal(2, 20)).await);
+
+    // assert
+    assert!(drain(&mut events).is_empty());
+}
+
+#[derive(Default)]
+struct Receiver {
+    // The first requests fail, until the counter reaches zero
+    failures: AtomicUsize,
+    received: Mutex<Vec<Value>>,
+}
+
+async fn receive(
+    State(receiver): State<Arc<Receiver>>,
+    axum::Json(event): axum::Json<Value>,
+) -> StatusCode {
+    let failed = receiver
+        .failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok();
+    if failed {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    receiver.received.lock().await.push(event);
+    StatusCode::NO_CONTENT
+}
+
+/// Serves a receiver on a random local port, that fails the given number of requests first
+async fn spawn_receiver(failures: usize) -> (String, Arc<Receiver>) {
+    let receiver = Arc::new(Receiver {
+        failures: AtomicUsize::new(failures),
+        ..Receiver::default()
+    });
+    let router = Router::new()
+        .route("/events", post(receive))
+        .with_state(Arc::clone(&receiver));
+    let listener = assert_ok!(TcpListener::bind("127.0.0.1:0").await);
+    let addr = assert_ok!(listener.local_addr());
+
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    (format!("http://{addr}/events"), receiver)
+}
+
+#[tokio::test]
+async fn webhook_retries_a_failed_delivery() {
+    // arrange
+    let (url, receiver) = spawn_receiver(2).await;
+    let Components { engine, .. } = Components::setup();
+    let webhook = Webhook::new(assert_ok!(url.parse()))
+        .retries(2)
+        .backoff(Duration::from_millis(1));
+    let delivery = webhook.spawn(engine.subscribe());
+
+    // act
+    assert_ok!(engine.process_record(deposit(1, 10)).await);
+    drop(engine);
+    assert_ok!(delivery.await);
+
+    // assert
+    let received = receiver.received.lock().await;
+    assert_eq!(
+        received.as_slice(),
+        [json!({
+            "event": "balance_changed",
+            "tx": 1,
+            "account": {"client": 1, "available": "10", "held": "0", "total": "10", "locked": false}
+        })]
+    );
+}
+
+#[tokio::test]
+async fn webhook_gives_up_after_the_last_retry() {
+    // arrange
+    let (url, receiver) = spawn_receiver(usize::MAX).await;
+    let webhook = Webhook::new(assert_ok!(url.parse()))
+        .retries(1)
+        .backoff(Duration::from_millis(1));
+    let event = AccountEvent::BalanceChanged {
+        tx: TransactionId::new(1),
+        account: Account::new(ClientId::new(1)),
+    };
+
+    // act
+    let res = webhook.deliver(&event).await;
+
+    // assert
+    assert_err!(res);
+    assert_eq!(
+        receiver.failures.load(Ordering::SeqCst),
+        usize::MAX - 2,
+        "Expected the first attempt and a single retry"
+    );
+}
@@ -5,11 +5,13 @@ use rust_decimal::dec;
 use toy_payment_engine::models::NonNegativeDecimal;
 use toy_payment_engine::models::client::ClientId;
 use toy_payment_engine::models::transaction::{
-    Deposit, TransactionId, TransactionStatus, TransactionType, TxRecord,
+    Deposit, Dispute, TransactionId, TransactionStatus, TransactionType, TxRecord, Withdrawal,
 };
 
 use setup::Components;
-use toy_payment_engine::prelude::{AccountRepository, TransactionRepository};
+use toy_payment_engine::prelude::{
+    AccountRepository, EngineConfig, IdempotencyPolicy, TransactionRepository,
+};
 
 mod setup;
 
@@ -34,7 +36,7 @@ async fn must_not_process_the_same_tx_multiple_times() {
     let txs = [deposit; 10].into_iter().map(TxRecord::from);
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -58,3 +60,174 @@ async fn must_not_process_the_same_tx_multiple_times() {
         "Unexpected tx_status"
     );
 }
+
+#[tokio::test]
+async fn failed_tx_can_be_retried_with_the_same_tx_id() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::setup();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(2);
+    let withdrawal = Withdrawal {
+        client_id,
+        tx_id,
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    };
+
+    let txs = [
+        // fails due to insufficient funds
+        TxRecord::from(withdrawal),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(42).unwrap(),
+        }),
+        // the retry succeeds
+        TxRecord::from(withdrawal),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(32), "unexpected available amount");
+    assert_eq!(account.total, dec!(32), "unexpected total amount");
+
+    let tx = assert_some!(transactions.get(tx_id).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Processed,
+        "Unexpected tx_status"
+    );
+}
+
+#[tokio::test]
+async fn failed_tx_can_not_be_retried_if_any_reuse_is_rejected() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::with_config(EngineConfig {
+        idempotency: IdempotencyPolicy::RejectAnyReuse,
+        ..EngineConfig::default()
+    });
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(2);
+    let withdrawal = Withdrawal {
+        client_id,
+        tx_id,
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    };
+
+    let txs = [
+        TxRecord::from(withdrawal),
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(42).unwrap(),
+        }),
+        TxRecord::from(withdrawal),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(42), "unexpected available amount");
+
+    let tx = assert_some!(transactions.get(tx_id).await);
+    assert_eq!(tx.status, TransactionStatus::Failed, "Unexpected tx_status");
+}
+
+#[tokio::test]
+async fn failed_tx_can_only_be_retried_by_its_client() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::setup();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+    let client_2 = ClientId::new(2);
+    let tx_id = TransactionId::new(2);
+
+    let txs = [
+        // fails due to insufficient funds
+        TxRecord::from(Withdrawal {
+            client_id: client_1,
+            tx_id,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_2,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(42).unwrap(),
+        }),
+        // rejected as duplicate, as the failed tx belongs to client 1
+        TxRecord::from(Withdrawal {
+            client_id: client_2,
+            tx_id,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_2).await));
+    assert_eq!(account.available, dec!(42), "unexpected available amount");
+
+    let tx = assert_some!(transactions.get(tx_id).await);
+    assert_eq!(tx.client_id, client_1, "Unexpected client_id");
+    assert_eq!(tx.status, TransactionStatus::Failed, "Unexpected tx_status");
+}
+
+#[tokio::test]
+async fn must_not_reuse_the_tx_id_of_a_disputed_tx() {
+    let Components {
+        engine,
+        accounts,
+        transactions,
+    } = Components::setup();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(1);
+    let deposit = Deposit {
+        client_id,
+        tx_id,
+        amount: NonNegativeDecimal::try_from(42).unwrap(),
+    };
+
+    let txs = [
+        TxRecord::from(deposit),
+        TxRecord::from(Dispute { client_id, tx_id }),
+        TxRecord::from(deposit),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(0), "unexpected available amount");
+    assert_eq!(account.held, dec!(42), "unexpected held amount");
+    assert_eq!(account.total, dec!(42), "unexpected total amount");
+
+    let tx = assert_some!(transactions.get(tx_id).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Disputed,
+        "Unexpected tx_status"
+    );
+}
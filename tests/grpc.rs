@@ -0,0 +1,145 @@
+use std::net::SocketAddr;
+
+use claims::{assert_err, assert_ok, assert_some};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::Code;
+use tonic::transport::{Channel, Server};
+
+use setup::Components;
+use toy_payment_engine::prelude::grpc_server;
+use toy_payment_engine::prelude::proto::outcome::Outcome;
+use toy_payment_engine::prelude::proto::payment_engine_client::PaymentEngineClient;
+use toy_payment_engine::prelude::proto::tx_record::Record;
+use toy_payment_engine::prelude::proto::{self, RejectionReason, TxRecord, WatchAccountsRequest};
+
+mod setup;
+
+/// Serves the gRPC service of a fresh engine on a random local port and connects a client
+async fn spawn_server() -> PaymentEngineClient<Channel> {
+    let Components { engine, .. } = Components::setup();
+    let listener = assert_ok!(TcpListener::bind("127.0.0.1:0").await);
+    let addr: SocketAddr = assert_ok!(listener.local_addr());
+
+    tokio::spawn(
+        Server::builder()
+            .add_service(grpc_server(engine))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    assert_ok!(PaymentEngineClient::connect(format!("http://{addr}")).await)
+}
+
+fn deposit(client: u32, tx: u32, amount: &str) -> TxRecord {
+    TxRecord {
+        record: Some(Record::Deposit(proto::Deposit {
+            client,
+            tx,
+            amount: amount.to_owned(),
+        })),
+    }
+}
+
+fn withdrawal(client: u32, tx: u32, amount: &str) -> TxRecord {
+    TxRecord {
+        record: Some(Record::Withdrawal(proto::Withdrawal {
+            client,
+            tx,
+            amount: amount.to_owned(),
+        })),
+    }
+}
+
+#[tokio::test]
+async fn applied_record_returns_the_account() {
+    let mut client = spawn_server().await;
+
+    // act
+    let outcome = assert_ok!(client.submit(deposit(1, 1, "10.5")).await).into_inner();
+
+    // assert
+    let Some(Outcome::Applied(applied)) = outcome.outcome else {
+        panic!("Expected an applied outcome, but got: {outcome:?}");
+    };
+    let account = assert_some!(applied.account);
+    assert_eq!(account.client, 1);
+    assert_eq!(account.available, "10.5");
+    assert!(!account.locked);
+}
+
+#[tokio::test]
+async fn rejected_record_returns_the_reason() {
+    let mut client = spawn_server().await;
+    assert_ok!(client.submit(deposit(1, 1, "10")).await);
+
+    // act
+    let outcome = assert_ok!(client.submit(withdrawal(1, 2, "20")).await).into_inner();
+
+    // assert
+    let Some(Outcome::Rejected(rejected)) = outcome.outcome else {
+        panic!("Expected a rejected outcome, but got: {outcome:?}");
+    };
+    assert_eq!(rejected.reason(), RejectionReason::InsufficientFunds);
+    assert!(rejected.message.contains("No sufficient funds"));
+}
+
+#[tokio::test]
+async fn invalid_record_is_an_invalid_argument() {
+    let mut client = spawn_server().await;
+
+    // act
+    let status = assert_err!(client.submit(deposit(70_000, 1, "1")).await);
+
+    // assert
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn batch_returns_the_outcomes_in_order() {
+    let mut client = spawn_server().await;
+    let records = vec![
+        deposit(1, 1, "10"),
+        deposit(1, 2, "one"),
+        withdrawal(1, 3, "20"),
+        withdrawal(1, 4, "5"),
+    ];
+
+    // act
+    let batch = assert_ok!(client.submit_batch(tokio_stream::iter(records)).await).into_inner();
+
+    // assert
+    let outcomes: Vec<_> = batch.outcomes.into_iter().map(|o| o.outcome).collect();
+    assert!(matches!(outcomes[0], Some(Outcome::Applied(_))));
+    assert!(matches!(outcomes[1], Some(Outcome::Malformed(_))));
+    assert!(matches!(outcomes[2], Some(Outcome::Rejected(_))));
+    let Some(Outcome::Applied(applied)) = &outcomes[3] else {
+        panic!("Expected an applied outcome, but got: {:?}", outcomes[3]);
+    };
+    assert_eq!(assert_some!(applied.account.as_ref()).available, "5");
+}
+
+#[tokio::test]
+async fn feed_streams_the_changes_of_the_watched_client() {
+    let mut client = spawn_server().await;
+    let mut feed = assert_ok!(
+        client
+            .watch_accounts(WatchAccountsRequest { client: Some(2) })
+            .await
+    )
+    .into_inner();
+
+    // act
+    assert_ok!(client.submit(deposit(1, 1, "10")).await);
+    assert_ok!(client.submit(deposit(2, 2, "3")).await);
+    assert_ok!(client.submit(withdrawal(2, 3, "5")).await);
+    assert_ok!(client.submit(withdrawal(2, 4, "1")).await);
+
+    // assert
+    let first = assert_some!(assert_ok!(feed.message().await));
+    assert_eq!(first.client, 2);
+    assert_eq!(first.available, "3");
+    // The rejected withdrawal didn't change the account
+    let second = assert_some!(assert_ok!(feed.message().await));
+    assert_eq!(second.client, 2);
+    assert_eq!(second.available, "2");
+}
@@ -0,0 +1,167 @@
+use claims::assert_ok;
+use futures::stream::{self, StreamExt};
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Deposit, Dispute, TransactionId, TransactionStatus, TxRecord, Withdrawal,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{CsvEncoder, JsonLinesEncoder, TransactionRepository};
+
+mod setup;
+
+#[tokio::test]
+async fn history_is_ordered_by_client_and_tx_id() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        // fails for insufficient funds
+        TxRecord::from(Withdrawal {
+            client_id: client_1,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(20).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_1,
+            tx_id: TransactionId::new(2),
+        }),
+    ]
+    .into_iter();
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let history: Vec<_> = transactions.history(None).collect().await;
+
+    // assert
+    let ids: Vec<_> = history
+        .iter()
+        .map(|tx| (tx.client_id, tx.id, tx.status))
+        .collect();
+    assert_eq!(
+        ids,
+        [
+            (
+                ClientId::new(1),
+                TransactionId::new(1),
+                TransactionStatus::Failed
+            ),
+            (
+                ClientId::new(1),
+                TransactionId::new(2),
+                TransactionStatus::Disputed
+            ),
+            (
+                ClientId::new(2),
+                TransactionId::new(3),
+                TransactionStatus::Processed
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn can_export_the_history_of_a_single_client_as_csv() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id: client_1,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(20).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_1,
+            tx_id: TransactionId::new(2),
+        }),
+        // another client, thus not exported
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        }),
+    ]
+    .into_iter();
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let mut sink = Vec::new();
+    let history = transactions.history(Some(client_1));
+    assert_ok!(CsvEncoder::encode_transactions(&mut sink, history).await);
+
+    // assert
+    assert_eq!(
+        String::from_utf8(sink).unwrap(),
+        "type,client,tx,amount,status\nwithdrawal,1,1,20,failed\ndeposit,1,2,10,disputed\n"
+    );
+}
+
+#[tokio::test]
+async fn can_export_the_history_as_json_lines() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let client_2 = ClientId::new(2);
+
+    let txs = [
+        Deposit {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        },
+        // another client, thus not exported
+        Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        },
+    ]
+    .into_iter()
+    .map(TxRecord::from);
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let mut sink = Vec::new();
+    let history = transactions.history(Some(client_2));
+    assert_ok!(JsonLinesEncoder::encode_transactions(&mut sink, history).await);
+
+    // assert
+    assert_eq!(
+        String::from_utf8(sink).unwrap(),
+        "{\"type\":\"deposit\",\"client\":2,\"tx\":3,\"amount\":\"5\",\"status\":\"processed\"}\n"
+    );
+}
@@ -0,0 +1,166 @@
+use std::net::SocketAddr;
+
+use claims::assert_ok;
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+
+use setup::Components;
+use toy_payment_engine::prelude::http_router;
+
+mod setup;
+
+/// Serves the API of a fresh engine on a random local port
+async fn spawn_server() -> SocketAddr {
+    let Components { engine, .. } = Components::setup();
+    let listener = assert_ok!(TcpListener::bind("127.0.0.1:0").await);
+    let addr = assert_ok!(listener.local_addr());
+
+    tokio::spawn(async move { axum::serve(listener, http_router(engine)).await });
+
+    addr
+}
+
+async fn post(addr: SocketAddr, body: Value) -> (StatusCode, Value) {
+    let response = assert_ok!(
+        Client::new()
+            .post(format!("http://{addr}/transactions"))
+            .json(&body)
+            .send()
+            .await
+    );
+    let status = response.status();
+
+    (status, assert_ok!(response.json().await))
+}
+
+async fn get(addr: SocketAddr, path: &str) -> (StatusCode, Value) {
+    let response = assert_ok!(
+        Client::new()
+            .get(format!("http://{addr}{path}"))
+            .send()
+            .await
+    );
+    let status = response.status();
+
+    (status, assert_ok!(response.json().await))
+}
+
+#[tokio::test]
+async fn applied_record_returns_the_account() {
+    let addr = spawn_server().await;
+
+    // act
+    let (status, body) = post(
+        addr,
+        json!({"type": "deposit", "client": 1, "tx": 1, "amount": "10.5"}),
+    )
+    .await;
+
+    // assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["outcome"], "applied");
+    assert_eq!(body["record"]["tx"], 1);
+    assert_eq!(body["account"]["available"], "10.5");
+}
+
+#[tokio::test]
+async fn rejected_record_carries_the_typed_reason() {
+    let addr = spawn_server().await;
+
+    // arrange
+    post(
+        addr,
+        json!({"type": "deposit", "client": 1, "tx": 1, "amount": "10"}),
+    )
+    .await;
+
+    // act
+    let (status, body) = post(
+        addr,
+        json!({"type": "withdrawal", "client": 1, "tx": 2, "amount": "20"}),
+    )
+    .await;
+
+    // assert
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["outcome"], "rejected");
+    assert_eq!(
+        body["rejection"],
+        json!({"reason": "insufficient_funds", "amount": "20", "available": "10"})
+    );
+}
+
+#[tokio::test]
+async fn batch_is_answered_per_record() {
+    let addr = spawn_server().await;
+
+    // act
+    let (status, body) = post(
+        addr,
+        json!([
+            {"type": "deposit", "client": 1, "tx": 1, "amount": "10"},
+            {"type": "deposit", "client": 1, "tx": 1, "amount": "10"},
+            {"type": "dispute", "client": 1, "tx": 7},
+        ]),
+    )
+    .await;
+
+    // assert
+    assert_eq!(status, StatusCode::OK);
+    let reasons: Vec<_> = assert_ok!(serde_json::from_value::<Vec<Value>>(body))
+        .iter()
+        .map(|response| response["rejection"]["reason"].clone())
+        .collect();
+    assert_eq!(
+        reasons,
+        [Value::Null, json!("duplicate"), json!("invalid_reference")]
+    );
+}
+
+#[tokio::test]
+async fn accounts_and_transactions_can_be_read() {
+    let addr = spawn_server().await;
+
+    // arrange
+    post(
+        addr,
+        json!([
+            {"type": "deposit", "client": 1, "tx": 1, "amount": "10"},
+            {"type": "deposit", "client": 2, "tx": 2, "amount": "5"},
+        ]),
+    )
+    .await;
+
+    // act
+    let (account_status, account) = get(addr, "/accounts/2").await;
+    let (page_status, page) = get(addr, "/accounts?limit=1").await;
+    let (tx_status, tx) = get(addr, "/transactions/1").await;
+
+    // assert
+    assert_eq!(account_status, StatusCode::OK);
+    assert_eq!(account["total"], "5");
+
+    assert_eq!(page_status, StatusCode::OK);
+    assert_eq!(page["items"].as_array().map(Vec::len), Some(1));
+    assert_eq!(page["next"], 1);
+
+    assert_eq!(tx_status, StatusCode::OK);
+    assert_eq!(tx["status"], "processed");
+}
+
+#[tokio::test]
+async fn unknown_resources_and_malformed_records_are_errors() {
+    let addr = spawn_server().await;
+
+    // act
+    let (account_status, _) = get(addr, "/accounts/9").await;
+    let (tx_status, _) = get(addr, "/transactions/9").await;
+    let (post_status, body) = post(addr, json!({"type": "refund", "client": 1, "tx": 1})).await;
+
+    // assert
+    assert_eq!(account_status, StatusCode::NOT_FOUND);
+    assert_eq!(tx_status, StatusCode::NOT_FOUND);
+    assert_eq!(post_status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].is_string(), "Expected an error message");
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use claims::{assert_ok, assert_some};
+use futures::stream::{self, BoxStream, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::journal::{JournalEntry, Posting, SubLedger, TrialBalance};
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{
+    AccountRepository, BalanceOrder, CsvEncoder, EngineConfig, EngineMetrics, FailureMode,
+    InMemoryAccountRepository, InMemoryJournal, InMemoryTxRepository, JournalRepository,
+    PaymentEngine,
+};
+
+mod setup;
+
+#[tokio::test]
+async fn only_applied_records_are_posted_balanced() {
+    let journal = Arc::new(InMemoryJournal::new());
+    let Components { engine, .. } = Components::builder().journal(&journal).build();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let withdrawal = TransactionId::new(2);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: withdrawal,
+            amount: NonNegativeDecimal::try_from(3).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id,
+            tx_id: withdrawal,
+        }),
+        TxRecord::from(Resolve {
+            client_id,
+            tx_id: withdrawal,
+        }),
+        // rejected, thus not posted
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(100).unwrap(),
+        }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let entries: Vec<_> = journal.entries().collect().await;
+    assert_eq!(entries.len(), 4, "unexpected number of entries");
+    assert!(
+        entries.iter().all(|entry| entry.is_balanced()),
+        "Expected every entry to be balanced"
+    );
+}
+
+#[tokio::test]
+async fn trial_balance_sums_up_to_zero() {
+    let journal = Arc::new(InMemoryJournal::new());
+    let Components { engine, .. } = Components::builder().journal(&journal).build();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+    let client_2 = ClientId::new(2);
+    let deposit = TransactionId::new(1);
+    let withdrawal = TransactionId::new(4);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id: deposit,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id: client_2,
+            tx_id: withdrawal,
+            amount: NonNegativeDecimal::try_from(3).unwrap(),
+        }),
+        // the disputed withdrawal is provisionally credited from the chargeback loss
+        TxRecord::from(Dispute {
+            client_id: client_2,
+            tx_id: withdrawal,
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+        TxRecord::from(Resolve {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+        TxRecord::from(Chargeback {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let trial_balance = assert_ok!(journal.trial_balance().await);
+    assert!(trial_balance.is_balanced(), "unexpected sum");
+
+    let balance_of = |sub_ledger| {
+        trial_balance
+            .lines
+            .iter()
+            .find(|line| line.sub_ledger == sub_ledger)
+            .map(|line| line.balance)
+    };
+    // 25 deposited, 3 withdrawn and 10 charged back
+    assert_eq!(balance_of(SubLedger::FiatClearing), Some(dec!(12)));
+    assert_eq!(balance_of(SubLedger::ChargebackLoss), Some(dec!(3)));
+}
+
+#[tokio::test]
+async fn accounts_are_a_projection_of_the_journal() {
+    let journal = Arc::new(InMemoryJournal::new());
+    let Components {
+        engine, accounts, ..
+    } = Components::builder().journal(&journal).build();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+    let client_2 = ClientId::new(2);
+    let deposit = TransactionId::new(1);
+    let withdrawal = TransactionId::new(3);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id: deposit,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_2,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id: client_2,
+            tx_id: withdrawal,
+            amount: NonNegativeDecimal::try_from(3).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_2,
+            tx_id: withdrawal,
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+        TxRecord::from(Resolve {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+        TxRecord::from(Chargeback {
+            client_id: client_1,
+            tx_id: deposit,
+        }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let projected = assert_ok!(journal.accounts().await);
+    let balances: Vec<_> = accounts.balances(BalanceOrder::ClientId).collect().await;
+    assert_eq!(projected, balances);
+}
+
+#[tokio::test]
+async fn trial_balance_is_encoded_as_csv() {
+    let journal = Arc::new(InMemoryJournal::new());
+    let Components { engine, .. } = Components::builder().journal(&journal).build();
+    let mut sink = Vec::new();
+
+    // arrange
+    let txs = [Deposit {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    }]
+    .into_iter()
+    .map(TxRecord::from);
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+    let trial_balance = assert_ok!(journal.trial_balance().await);
+
+    // act
+    assert_ok!(CsvEncoder::encode_trial_balance(&mut sink, &trial_balance).await);
+
+    // assert
+    assert_eq!(
+        String::from_utf8(sink).unwrap(),
+        "ledger,client,balance\nclient_available,1,-10\nfiat_clearing,,10\n"
+    );
+}
+
+/// Fails every post, e.g. like a journal on an unreachable database
+struct UnavailableJournal;
+
+#[async_trait]
+impl JournalRepository for UnavailableJournal {
+    async fn post(&self, _record: TxRecord, _postings: Vec<Posting>) -> Result<JournalEntry> {
+        bail!("The journal is unavailable")
+    }
+
+    fn entries(&self) -> BoxStream<'_, JournalEntry> {
+        stream::empty().boxed()
+    }
+
+    async fn trial_balance(&self) -> Result<TrialBalance> {
+        bail!("The journal is unavailable")
+    }
+
+    async fn accounts(&self) -> Result<Vec<Account>> {
+        bail!("The journal is unavailable")
+    }
+}
+
+#[tokio::test]
+async fn failing_journal_does_not_fail_the_applied_record() {
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let engine =
+        PaymentEngine::builder(Arc::clone(&accounts), Arc::new(InMemoryTxRepository::new()))
+            .config(EngineConfig {
+                failure_mode: FailureMode::Strict,
+                ..EngineConfig::default()
+            })
+            .journal(Arc::new(UnavailableJournal))
+            .metrics(Arc::new(assert_ok!(EngineMetrics::new())))
+            .build();
+
+    // arrange
+    let client_id = ClientId::new(1);
+
+    let txs = [
+        Deposit {
+            client_id,
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        },
+        Deposit {
+            client_id,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        },
+    ]
+    .into_iter()
+    .map(TxRecord::from);
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(15));
+    let metrics = assert_some!(assert_ok!(engine.encode_metrics().await));
+    for line in [
+        r#"payment_engine_records_total{outcome="applied",type="deposit"} 2"#,
+        r#"payment_engine_sink_failures_total{sink="journal"} 2"#,
+    ] {
+        assert!(metrics.contains(line), "Missing {line} in:\n{metrics}");
+    }
+}
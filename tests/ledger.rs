@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use claims::{assert_matches, assert_ok};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::ledger::{Outcome, reconstruct_balances};
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{
+    AccountRepository, BalanceOrder, InMemoryLedger, JsonLinesEncoder, LedgerRepository,
+};
+
+mod setup;
+
+#[tokio::test]
+async fn every_record_is_appended_with_its_outcome() {
+    let ledger = Arc::new(InMemoryLedger::new());
+    let Components { engine, .. } = Components::builder().ledger(&ledger).build();
+
+    // arrange
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(1);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id,
+            tx_id,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        // rejected for insufficient funds
+        TxRecord::from(Withdrawal {
+            client_id,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(20).unwrap(),
+        }),
+        TxRecord::from(Dispute { client_id, tx_id }),
+        TxRecord::from(Resolve { client_id, tx_id }),
+        TxRecord::from(Chargeback { client_id, tx_id }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let entries: Vec<_> = ledger.entries(None).collect().await;
+    let seqs: Vec<_> = entries.iter().map(|entry| entry.seq).collect();
+    assert_eq!(seqs, [1, 2, 3, 4, 5], "unexpected sequence numbers");
+
+    let rejected = &entries[1];
+    assert_matches!(&rejected.outcome, Outcome::Rejected { .. });
+    assert_eq!(rejected.before, rejected.after, "Expected no change");
+
+    let chargeback = &entries[4];
+    assert!(chargeback.outcome.is_applied(), "unexpected outcome");
+    assert_eq!(chargeback.after.unwrap().total, dec!(0));
+    assert!(chargeback.after.unwrap().is_locked, "unexpected is_locked");
+}
+
+#[tokio::test]
+async fn entries_can_be_queried_per_client() {
+    let ledger = Arc::new(InMemoryLedger::new());
+    let Components { engine, .. } = Components::builder().ledger(&ledger).build();
+
+    // arrange
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(1),
+            tx_id: TransactionId::new(1),
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(3).unwrap(),
+        }),
+        // rejected, but still appended
+        TxRecord::from(Withdrawal {
+            client_id: ClientId::new(2),
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(5).unwrap(),
+        }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let entries: Vec<_> = ledger.entries(Some(ClientId::new(2))).collect().await;
+    let seqs: Vec<_> = entries.iter().map(|entry| entry.seq).collect();
+    assert_eq!(seqs, [2, 3], "unexpected entries of client 2");
+}
+
+#[tokio::test]
+async fn balances_can_be_reconstructed_from_the_ledger() {
+    let ledger = Arc::new(InMemoryLedger::new());
+    let Components {
+        engine, accounts, ..
+    } = Components::builder().ledger(&ledger).build();
+
+    // arrange
+    let client_1 = ClientId::new(1);
+    let client_2 = ClientId::new(2);
+    let tx_id = TransactionId::new(1);
+
+    let txs = [
+        TxRecord::from(Deposit {
+            client_id: client_1,
+            tx_id,
+            amount: NonNegativeDecimal::try_from(10).unwrap(),
+        }),
+        TxRecord::from(Deposit {
+            client_id: client_2,
+            tx_id: TransactionId::new(2),
+            amount: NonNegativeDecimal::try_from(3).unwrap(),
+        }),
+        TxRecord::from(Withdrawal {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+            amount: NonNegativeDecimal::try_from(2).unwrap(),
+        }),
+        TxRecord::from(Dispute {
+            client_id: client_1,
+            tx_id,
+        }),
+        TxRecord::from(Resolve {
+            client_id: client_1,
+            tx_id,
+        }),
+        TxRecord::from(Chargeback {
+            client_id: client_1,
+            tx_id,
+        }),
+    ]
+    .into_iter();
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let entries: Vec<_> = ledger.entries(None).collect().await;
+    let reconstructed = assert_ok!(reconstruct_balances(entries));
+    let balances: Vec<_> = accounts.balances(BalanceOrder::ClientId).collect().await;
+    assert_eq!(reconstructed, balances);
+}
+
+#[tokio::test]
+async fn ledger_is_encoded_as_json_lines() {
+    let ledger = Arc::new(InMemoryLedger::new());
+    let Components { engine, .. } = Components::builder().ledger(&ledger).build();
+    let mut sink = Vec::new();
+
+    // arrange
+    let txs = [Deposit {
+        client_id: ClientId::new(1),
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    }]
+    .into_iter()
+    .map(TxRecord::from);
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    assert_ok!(JsonLinesEncoder::encode_ledger(&mut sink, ledger.entries(None)).await);
+
+    // assert
+    assert_eq!(
+        String::from_utf8(sink).unwrap(),
+        concat!(
+            r#"{"seq":1,"record":{"type":"deposit","client":1,"tx":1,"amount":"10"},"outcome":"applied","#,
+            r#""before":null,"after":{"client":1,"available":"10","held":"0","total":"10","locked":false}}"#,
+            "\n"
+        )
+    );
+}
@@ -0,0 +1,108 @@
+use claims::{assert_err, assert_ok, assert_some};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::rejection::{Limit, Rejection};
+use toy_payment_engine::models::transaction::{Deposit, TransactionId, TxRecord, Withdrawal};
+
+use setup::Components;
+use toy_payment_engine::prelude::{
+    AccountRepository, EngineConfig, LimitsConfig, TxLimits, Velocity,
+};
+
+mod setup;
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn withdrawal(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Withdrawal(Withdrawal {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn with_limits(limits: LimitsConfig) -> Components {
+    Components::with_config(EngineConfig {
+        limits,
+        ..EngineConfig::default()
+    })
+}
+
+fn assert_limit_exceeded(res: anyhow::Result<()>, expected: Limit) {
+    let err = assert_err!(res);
+    let rejection = assert_some!(Rejection::of(&err));
+    assert_eq!(
+        *rejection,
+        Rejection::LimitExceeded {
+            client: ClientId::new(1),
+            limit: expected,
+        }
+    );
+}
+
+#[tokio::test]
+async fn withdrawals_are_limited_per_record_and_per_day() {
+    // arrange
+    let Components {
+        engine, accounts, ..
+    } = with_limits(LimitsConfig {
+        withdrawal: TxLimits {
+            max_amount: Some(dec!(50)),
+            daily_amount: Some(dec!(80)),
+            velocity: None,
+        },
+        ..LimitsConfig::default()
+    });
+    assert_ok!(engine.process_record(deposit(1, 1, 1000)).await);
+
+    // act & assert
+    assert_limit_exceeded(
+        engine.process_record(withdrawal(1, 2, 60)).await,
+        Limit::MaxAmount,
+    );
+    assert_ok!(engine.process_record(withdrawal(1, 3, 50)).await);
+    assert_limit_exceeded(
+        engine.process_record(withdrawal(1, 4, 40)).await,
+        Limit::DailyAmount,
+    );
+    assert_ok!(engine.process_record(withdrawal(1, 5, 30)).await);
+
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.available, dec!(920));
+}
+
+#[tokio::test]
+async fn rejected_records_do_not_count_towards_the_velocity() {
+    // arrange
+    let Components { engine, .. } = with_limits(LimitsConfig {
+        withdrawal: TxLimits {
+            velocity: Some(Velocity {
+                count: 2,
+                window_secs: 60,
+            }),
+            ..TxLimits::default()
+        },
+        ..LimitsConfig::default()
+    });
+    assert_ok!(engine.process_record(deposit(1, 1, 10)).await);
+    assert_ok!(engine.process_record(deposit(2, 2, 10)).await);
+
+    // act & assert
+    assert_err!(engine.process_record(withdrawal(1, 3, 100)).await);
+    assert_ok!(engine.process_record(withdrawal(1, 4, 1)).await);
+    assert_ok!(engine.process_record(withdrawal(1, 5, 1)).await);
+    assert_limit_exceeded(
+        engine.process_record(withdrawal(1, 6, 1)).await,
+        Limit::Velocity,
+    );
+    // The limits are per client
+    assert_ok!(engine.process_record(withdrawal(2, 7, 1)).await);
+}
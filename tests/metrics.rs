@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use claims::{assert_err, assert_ok, assert_some};
+use tokio::net::TcpListener;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Chargeback, Deposit, Dispute, Resolve, TransactionId, TxRecord, Withdrawal,
+};
+use toy_payment_engine::prelude::{
+    EngineMetrics, InMemoryAccountRepository, InMemoryTxRepository, PaymentEngine, http_router,
+};
+
+fn engine_with_metrics() -> PaymentEngine<InMemoryAccountRepository, InMemoryTxRepository> {
+    PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    )
+    .metrics(Arc::new(assert_ok!(EngineMetrics::new())))
+    .build()
+}
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn assert_contains(text: &str, lines: &[&str]) {
+    for line in lines {
+        assert!(text.contains(line), "Missing {line} in:\n{text}");
+    }
+}
+
+#[tokio::test]
+async fn records_and_gauges_are_measured() {
+    // arrange
+    let engine = engine_with_metrics();
+    let client_1 = ClientId::new(1);
+    let client_2 = ClientId::new(2);
+
+    // act
+    assert_ok!(engine.process_record(deposit(1, 1, 10)).await);
+    assert_ok!(engine.process_record(deposit(1, 2, 5)).await);
+    assert_ok!(engine.process_record(deposit(2, 3, 10)).await);
+    assert_err!(
+        engine
+            .process_record(TxRecord::Withdrawal(Withdrawal {
+                client_id: client_1,
+                tx_id: TransactionId::new(4),
+                amount: NonNegativeDecimal::try_from(100).unwrap(),
+            }))
+            .await
+    );
+    for record in [
+        TxRecord::Dispute(Dispute {
+            client_id: client_1,
+            tx_id: TransactionId::new(1),
+        }),
+        TxRecord::Dispute(Dispute {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+        }),
+        TxRecord::Resolve(Resolve {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+        }),
+        TxRecord::Chargeback(Chargeback {
+            client_id: client_2,
+            tx_id: TransactionId::new(3),
+        }),
+    ] {
+        assert_ok!(engine.process_record(record).await);
+    }
+    let text = assert_some!(assert_ok!(engine.encode_metrics().await));
+
+    // assert
+    assert_contains(
+        &text,
+        &[
+            r#"payment_engine_records_total{outcome="applied",type="deposit"} 3"#,
+            r#"payment_engine_records_total{outcome="rejected",type="withdrawal"} 1"#,
+            r#"payment_engine_rejections_total{reason="insufficient_funds",type="withdrawal"} 1"#,
+            r#"payment_engine_record_duration_seconds_count{type="dispute"} 2"#,
+            r#"payment_engine_repository_duration_seconds_count{operation="get_or_new",repository="accounts"} 3"#,
+            r#"payment_engine_repository_duration_seconds_count{operation="update_status",repository="transactions"} 4"#,
+            "payment_engine_accounts 2",
+            "payment_engine_locked_accounts 1",
+            "payment_engine_open_disputes 1",
+        ],
+    );
+}
+
+#[tokio::test]
+async fn engine_without_metrics_encodes_nothing() {
+    let engine = PaymentEngine::new(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    );
+
+    assert_eq!(assert_ok!(engine.encode_metrics().await), None);
+}
+
+#[tokio::test]
+async fn metrics_are_served_over_http() {
+    // arrange
+    let engine = engine_with_metrics();
+    assert_ok!(engine.process_record(deposit(1, 1, 10)).await);
+    let listener = assert_ok!(TcpListener::bind("127.0.0.1:0").await);
+    let addr = assert_ok!(listener.local_addr());
+    tokio::spawn(async move { axum::serve(listener, http_router(engine)).await });
+
+    // act
+    let response = assert_ok!(reqwest::get(format!("http://{addr}/metrics")).await);
+
+    // assert
+    assert!(response.status().is_success());
+    let text = assert_ok!(response.text().await);
+    assert_contains(
+        &text,
+        &[
+            r#"payment_engine_records_total{outcome="applied",type="deposit"} 1"#,
+            "payment_engine_accounts 1",
+        ],
+    );
+}
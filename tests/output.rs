@@ -0,0 +1,170 @@
+use claims::assert_ok;
+use futures::stream::{self, StreamExt};
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{Deposit, TransactionId, TxRecord};
+
+use setup::Components;
+use toy_payment_engine::prelude::{
+    AccountRepository, BalanceOrder, CsvEncoder, JsonEncoder, JsonLinesEncoder,
+};
+
+mod setup;
+
+fn deposit(client_id: u16, tx_id: u32, amount: u32) -> TxRecord {
+    TxRecord::from(Deposit {
+        client_id: ClientId::new(client_id),
+        tx_id: TransactionId::new(tx_id),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+#[tokio::test]
+async fn can_stream_balances_into_the_csv_encoder() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [deposit(1, 1, 10), deposit(2, 2, 20), deposit(1, 3, 5)];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let mut sink = Vec::new();
+    assert_ok!(
+        CsvEncoder::encode_balances(&mut sink, accounts.balances(BalanceOrder::ClientId)).await
+    );
+
+    // assert
+    let output = String::from_utf8(sink).unwrap();
+    assert_eq!(
+        output,
+        "client,available,held,total,locked\n1,15,0,15,false\n2,20,0,20,false\n"
+    );
+}
+
+#[tokio::test]
+async fn balances_are_ordered_by_client_id() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = (0..100u16)
+        .rev()
+        .map(|client_id| deposit(client_id * 7 % 100, u32::from(client_id), 1));
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let client_ids: Vec<_> = accounts
+        .balances(BalanceOrder::ClientId)
+        .map(|acc| acc.client_id)
+        .collect()
+        .await;
+
+    // assert
+    let expected: Vec<_> = (0..100).map(ClientId::new).collect();
+    assert_eq!(client_ids, expected);
+}
+
+#[tokio::test]
+async fn balances_are_ordered_by_total_descending() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(1, 1, 10),
+        deposit(2, 2, 30),
+        deposit(3, 3, 20),
+        deposit(4, 4, 10),
+        // changes the order of client 1 by updating the account
+        deposit(1, 5, 25),
+    ];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let client_ids: Vec<_> = accounts
+        .balances(BalanceOrder::TotalDesc)
+        .map(|acc| acc.client_id)
+        .collect()
+        .await;
+
+    // assert
+    let expected: Vec<_> = [1, 2, 3, 4].into_iter().map(ClientId::new).collect();
+    assert_eq!(client_ids, expected);
+}
+
+#[tokio::test]
+async fn can_encode_balances_as_json() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [deposit(1, 1, 10), deposit(2, 2, 20)];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let mut sink = Vec::new();
+    assert_ok!(
+        JsonEncoder::encode_balances(&mut sink, accounts.balances(BalanceOrder::ClientId)).await
+    );
+
+    // assert
+    let output = String::from_utf8(sink).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"[{"client":1,"available":"10","held":"0","total":"10","locked":false},"#,
+            r#"{"client":2,"available":"20","held":"0","total":"20","locked":false}]"#,
+            "\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn can_encode_empty_balances_as_json() {
+    let Components { accounts, .. } = Components::setup();
+
+    // act
+    let mut sink = Vec::new();
+    assert_ok!(
+        JsonEncoder::encode_balances(&mut sink, accounts.balances(BalanceOrder::ClientId)).await
+    );
+
+    // assert
+    assert_eq!(String::from_utf8(sink).unwrap(), "[]\n");
+}
+
+#[tokio::test]
+async fn can_encode_balances_as_json_lines() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [deposit(1, 1, 10), deposit(2, 2, 20)];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let mut sink = Vec::new();
+    assert_ok!(
+        JsonLinesEncoder::encode_balances(&mut sink, accounts.balances(BalanceOrder::ClientId))
+            .await
+    );
+
+    // assert
+    let output = String::from_utf8(sink).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"{"client":1,"available":"10","held":"0","total":"10","locked":false}"#,
+            "\n",
+            r#"{"client":2,"available":"20","held":"0","total":"20","locked":false}"#,
+            "\n"
+        )
+    );
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use claims::{assert_err, assert_ok, assert_some, assert_some_eq};
+use futures::StreamExt;
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::rejection::Rejection;
+use toy_payment_engine::models::transaction::{Deposit, TransactionId, TxRecord, Withdrawal};
+use toy_payment_engine::prelude::{
+    AccountRepository, Decision, EngineMetrics, InMemoryAccountRepository, InMemoryTxRepository,
+    PaymentEngine, PolicyContext, TxPolicy,
+};
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn withdrawal(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Withdrawal(Withdrawal {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+/// Rejects withdrawals of clients with less than two stored transactions, i.e. without much history
+struct NewClientWithdrawals;
+
+#[async_trait]
+impl TxPolicy for NewClientWithdrawals {
+    fn name(&self) -> &'static str {
+        "new_client_withdrawals"
+    }
+
+    async fn evaluate(&self, record: &TxRecord, context: &PolicyContext<'_>) -> Result<Decision> {
+        if !matches!(record, TxRecord::Withdrawal(_)) {
+            return Ok(Decision::Allow);
+        }
+
+        let history = context
+            .transactions
+            .history(Some(record.client_id()))
+            .count()
+            .await;
+        match history < 2 {
+            true => Ok(Decision::Reject(
+                format!("{history} of 2 required transactions").into(),
+            )),
+            false => Ok(Decision::Allow),
+        }
+    }
+}
+
+/// Flags records of clients holding more than the threshold, and remembers the evaluated records
+struct LargeBalances {
+    evaluated: Mutex<Vec<TransactionId>>,
+}
+
+#[async_trait]
+impl TxPolicy for LargeBalances {
+    fn name(&self) -> &'static str {
+        "large_balances"
+    }
+
+    async fn evaluate(&self, record: &TxRecord, context: &PolicyContext<'_>) -> Result<Decision> {
+        self.evaluated.lock().unwrap().push(record.tx_id());
+
+        match context.account {
+            Some(account) if account.total > dec!(100) => {
+                Ok(Decision::Flag("large_balance".into()))
+            }
+            _ => Ok(Decision::Allow),
+        }
+    }
+}
+
+#[tokio::test]
+async fn first_rejecting_policy_rejects_the_record() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let large_balances = Arc::new(LargeBalances {
+        evaluated: Mutex::new(Vec::new()),
+    });
+    let engine =
+        PaymentEngine::builder(Arc::clone(&accounts), Arc::new(InMemoryTxRepository::new()))
+            .policy(Arc::new(NewClientWithdrawals))
+            .policy(Arc::clone(&large_balances) as Arc<dyn TxPolicy>)
+            .build();
+
+    // act
+    assert_ok!(engine.process_record(deposit(1, 1, 10)).await);
+    let rejected = engine.process_record(withdrawal(1, 2, 5)).await;
+    assert_ok!(engine.process_record(deposit(1, 3, 10)).await);
+    assert_ok!(engine.process_record(withdrawal(1, 4, 5)).await);
+
+    // assert
+    let err = assert_err!(rejected);
+    assert_some_eq!(
+        Rejection::of(&err),
+        &Rejection::PolicyRejected {
+            policy: "new_client_withdrawals",
+            cause: "1 of 2 required transactions".into(),
+        }
+    );
+    // The rejected withdrawal didn't reach the second policy
+    assert_eq!(
+        *large_balances.evaluated.lock().unwrap(),
+        [1, 3, 4].map(TransactionId::new)
+    );
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.available, dec!(15));
+}
+
+#[tokio::test]
+async fn flagged_record_is_applied_and_counted() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let engine =
+        PaymentEngine::builder(Arc::clone(&accounts), Arc::new(InMemoryTxRepository::new()))
+            .policy(Arc::new(LargeBalances {
+                evaluated: Mutex::new(Vec::new()),
+            }))
+            .metrics(Arc::new(assert_ok!(EngineMetrics::new())))
+            .build();
+
+    // act
+    assert_ok!(engine.process_record(deposit(1, 1, 200)).await);
+    assert_ok!(engine.process_record(deposit(1, 2, 50)).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.available, dec!(250));
+    let metrics = assert_some!(assert_ok!(engine.encode_metrics().await));
+    let flagged = r#"payment_engine_flags_total{cause="large_balance",policy="large_balances"} 1"#;
+    assert!(
+        metrics.contains(flagged),
+        "Missing {flagged} in:\n{metrics}"
+    );
+}
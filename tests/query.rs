@@ -0,0 +1,192 @@
+use claims::{assert_none, assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Deposit, Dispute, Resolve, Transaction, TransactionId, TransactionStatus, TransactionType,
+    TxRecord, Withdrawal,
+};
+
+use setup::Components;
+use toy_payment_engine::prelude::{TransactionRepository, TxQuery};
+
+mod setup;
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Deposit(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn withdrawal(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::Withdrawal(Withdrawal {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+fn dispute(client: u16, tx: u32) -> TxRecord {
+    TxRecord::Dispute(Dispute {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+    })
+}
+
+fn resolve(client: u16, tx: u32) -> TxRecord {
+    TxRecord::Resolve(Resolve {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+    })
+}
+
+fn ids(txs: &[Transaction]) -> Vec<u32> {
+    txs.iter().map(|tx| tx.id.into_inner()).collect()
+}
+
+#[tokio::test]
+async fn query_open_disputes_of_a_client() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(1, 1, 10),
+        deposit(1, 2, 10),
+        deposit(1, 3, 10),
+        deposit(2, 4, 10),
+        dispute(1, 1),
+        dispute(1, 3),
+        resolve(1, 3),
+        // another client
+        dispute(2, 4),
+    ];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+    let query = TxQuery::new()
+        .client(ClientId::new(1))
+        .status(TransactionStatus::Disputed);
+
+    // act
+    let page = assert_ok!(transactions.query(&query).await);
+    let count = assert_ok!(transactions.count(&query).await);
+
+    // assert
+    assert_eq!(ids(&page.items), [1], "unexpected open disputes");
+    assert_none!(page.next, "Expected a single page");
+    assert_eq!(count, 1, "unexpected count");
+}
+
+#[tokio::test]
+async fn query_by_type_and_status() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(1, 1, 10),
+        deposit(1, 2, 10),
+        deposit(1, 3, 10),
+        deposit(2, 4, 10),
+        dispute(2, 4),
+        // fails, as the funds are held
+        withdrawal(2, 5, 1),
+        dispute(1, 1),
+        dispute(1, 3),
+        resolve(1, 3),
+    ];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // act
+    let withdrawals = assert_ok!(
+        transactions
+            .query(&TxQuery::new().tx_type(TransactionType::Withdrawal))
+            .await
+    );
+    let resolved = assert_ok!(
+        transactions
+            .query(&TxQuery::new().status(TransactionStatus::Resolved))
+            .await
+    );
+    let disputed_deposits = assert_ok!(
+        transactions
+            .count(
+                &TxQuery::new()
+                    .tx_type(TransactionType::Deposit)
+                    .status(TransactionStatus::Disputed)
+            )
+            .await
+    );
+
+    // assert
+    assert_eq!(ids(&withdrawals.items), [5], "unexpected withdrawals");
+    assert_eq!(ids(&resolved.items), [3], "unexpected resolved txs");
+    assert_eq!(
+        disputed_deposits, 2,
+        "unexpected count of disputed deposits"
+    );
+}
+
+#[tokio::test]
+async fn query_without_filter_is_paginated_in_tx_id_order() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [
+        deposit(2, 5, 10),
+        deposit(1, 1, 10),
+        deposit(1, 3, 10),
+        deposit(2, 2, 10),
+        withdrawal(2, 6, 1),
+        deposit(1, 4, 10),
+    ];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+    let query = TxQuery::new().limit(4);
+
+    // act
+    let first = assert_ok!(transactions.query(&query).await);
+    let cursor = assert_some!(first.next, "Expected a second page");
+    let second = assert_ok!(transactions.query(&query.after(cursor)).await);
+
+    // assert
+    assert_eq!(ids(&first.items), [1, 2, 3, 4], "unexpected first page");
+    assert_eq!(ids(&second.items), [5, 6], "unexpected second page");
+    assert_none!(second.next, "Expected no third page");
+    assert_eq!(assert_ok!(transactions.count(&TxQuery::new()).await), 6);
+}
+
+#[tokio::test]
+async fn query_of_unknown_client_is_empty() {
+    let Components {
+        engine,
+        transactions,
+        ..
+    } = Components::setup();
+
+    // arrange
+    let txs = [deposit(1, 1, 10), deposit(2, 2, 10)];
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+    let query = TxQuery::new()
+        .client(ClientId::new(3))
+        .tx_type(TransactionType::Deposit);
+
+    // act
+    let page = assert_ok!(transactions.query(&query).await);
+    let count = assert_ok!(transactions.count(&query).await);
+
+    // assert
+    assert!(page.items.is_empty(), "Expected no transactions");
+    assert_eq!(count, 0, "unexpected count");
+}
@@ -0,0 +1,104 @@
+use std::io::Cursor;
+
+use claims::{assert_err, assert_ok};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::account::Account;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{Deposit, TransactionId, TxRecord};
+use toy_payment_engine::prelude::{
+    AccountField, AccountRepository, BalanceOrder, CsvDecoder, CsvEncoder, DiscrepancyKind,
+    FieldValue, reconcile,
+};
+
+use setup::Components;
+
+mod setup;
+
+fn deposit(client: u16, tx: u32, amount: u32) -> TxRecord {
+    TxRecord::from(Deposit {
+        client_id: ClientId::new(client),
+        tx_id: TransactionId::new(tx),
+        amount: NonNegativeDecimal::try_from(amount).unwrap(),
+    })
+}
+
+async fn actual_balances(records: Vec<TxRecord>) -> Vec<Account> {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+
+    assert_ok!(engine.process(stream::iter(records).fuse()).await);
+
+    accounts.balances(BalanceOrder::ClientId).collect().await
+}
+
+async fn decode(csv: &str) -> Vec<Account> {
+    assert_ok!(
+        CsvDecoder::new(Cursor::new(csv.to_owned()))
+            .decode_balances()
+            .try_collect()
+            .await
+    )
+}
+
+#[tokio::test]
+async fn matching_balances_are_reconciled() {
+    // arrange
+    let actual = actual_balances(vec![deposit(1, 1, 10), deposit(2, 2, 5)]).await;
+    let expected = decode(
+        "client,available,held,total,locked\n1,10.0000,0,10.0000,false\n2, 5, 0, 5, false\n",
+    )
+    .await;
+
+    // act
+    let reconciliation = reconcile(expected, actual);
+
+    // assert
+    assert!(reconciliation.is_reconciled(), "unexpected discrepancies");
+}
+
+#[tokio::test]
+async fn discrepancies_are_reported_and_encoded() {
+    // arrange
+    let actual = actual_balances(vec![deposit(1, 1, 10), deposit(3, 2, 5)]).await;
+    let expected =
+        decode("client,available,held,total,locked\n1,12,0,12,false\n2,0,0,0,false\n").await;
+
+    // act
+    let reconciliation = reconcile(expected, actual);
+
+    // assert
+    let available = &reconciliation.discrepancies[0];
+    assert_eq!(available.kind, DiscrepancyKind::Mismatch);
+    assert_eq!(available.field, Some(AccountField::Available));
+    assert_eq!(available.expected, Some(FieldValue::Amount(dec!(12))));
+    assert_eq!(available.delta, Some(dec!(-2)));
+
+    let mut sink = Vec::new();
+    assert_ok!(CsvEncoder::encode_reconciliation(&mut sink, &reconciliation).await);
+    assert_eq!(
+        String::from_utf8(sink).unwrap(),
+        "client,kind,field,expected,actual,delta\n\
+         1,mismatch,available,12,10,-2\n\
+         1,mismatch,total,12,10,-2\n\
+         2,missing,,,,\n\
+         3,unexpected,,,,\n"
+    );
+}
+
+#[tokio::test]
+async fn malformed_expected_balances_are_errors() {
+    // act
+    let decoded: Result<Vec<Account>, _> = CsvDecoder::new(Cursor::new(
+        "client,available,held,total,locked\n1,ten,0,10,false\n",
+    ))
+    .decode_balances()
+    .try_collect()
+    .await;
+
+    // assert
+    assert_err!(decoded);
+}
@@ -49,7 +49,7 @@ async fn can_resolve_a_deposit() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -109,7 +109,7 @@ async fn can_resolve_a_withdrawal() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use claims::{assert_none, assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Deposit, Dispute, TransactionId, TransactionStatus, TxRecord,
+};
+use toy_payment_engine::prelude::{
+    AccountRepository, InMemoryAccountRepository, InMemoryTxRepository, PaymentEngine,
+    TransactionRepository, TxRetention,
+};
+
+fn deposit(client_id: ClientId, tx_id: u32) -> TxRecord {
+    TxRecord::from(Deposit {
+        client_id,
+        tx_id: TransactionId::new(tx_id),
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    })
+}
+
+#[tokio::test]
+async fn only_the_most_recent_transactions_are_retained() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::with_retention(TxRetention::Count(2)));
+    let engine = PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions));
+
+    let client_id = ClientId::new(1);
+    let txs = [
+        deposit(client_id, 1),
+        deposit(client_id, 2),
+        deposit(client_id, 3),
+        // outside of the dispute window
+        TxRecord::from(Dispute {
+            client_id,
+            tx_id: TransactionId::new(1),
+        }),
+        // within the dispute window
+        TxRecord::from(Dispute {
+            client_id,
+            tx_id: TransactionId::new(3),
+        }),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(20), "unexpected available amount");
+    assert_eq!(account.held, dec!(10), "unexpected held amount");
+
+    assert_none!(transactions.get(TransactionId::new(1)).await);
+    assert!(transactions.is_expired(TransactionId::new(1)).await);
+
+    let tx = assert_some!(transactions.get(TransactionId::new(3)).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Disputed,
+        "Unexpected tx_status"
+    );
+}
+
+#[tokio::test]
+async fn expired_tx_ids_are_still_rejected_as_duplicates() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::with_retention(TxRetention::Count(1)));
+    let engine = PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions));
+
+    let client_id = ClientId::new(1);
+    let txs = [
+        deposit(client_id, 1),
+        deposit(client_id, 2),
+        // replay of an expired transaction
+        deposit(client_id, 1),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(20), "unexpected available amount");
+    assert_eq!(account.total, dec!(20), "unexpected total amount");
+}
+
+#[tokio::test(start_paused = true)]
+async fn transactions_expire_after_the_retention_duration() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::with_retention(TxRetention::Duration(
+        Duration::from_secs(60),
+    )));
+    let engine = PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions));
+
+    let client_id = ClientId::new(1);
+    let tx_id = TransactionId::new(1);
+
+    // act
+    assert_ok!(
+        engine
+            .process(stream::iter([deposit(client_id, 1)]).fuse())
+            .await
+    );
+    tokio::time::advance(Duration::from_secs(61)).await;
+    assert_ok!(
+        engine
+            .process(stream::iter([TxRecord::from(Dispute { client_id, tx_id })]).fuse())
+            .await
+    );
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(10), "unexpected available amount");
+    assert_eq!(account.held, dec!(0), "unexpected held amount");
+
+    assert_none!(transactions.get(tx_id).await);
+    assert!(transactions.is_expired(tx_id).await);
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use claims::{assert_ok, assert_some};
+use futures::stream::{self, StreamExt};
+use rust_decimal::dec;
+
+use toy_payment_engine::models::NonNegativeDecimal;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::models::transaction::{
+    Deposit, TransactionId, TransactionStatus, TxRecord, Withdrawal,
+};
+use toy_payment_engine::prelude::{
+    AccountRepository, BitmapSeenIndex, InMemoryAccountRepository, InMemoryTxRepository,
+    PaymentEngine, RoaringSeenIndex, SeenTxIndex, TransactionRepository,
+};
+
+async fn process_with_index(seen_index: Arc<dyn SeenTxIndex>) {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::new());
+    let engine = PaymentEngine::builder(Arc::clone(&accounts), Arc::clone(&transactions))
+        .seen_index(Arc::clone(&seen_index))
+        .build();
+
+    let client_id = ClientId::new(1);
+    let deposit = Deposit {
+        client_id,
+        tx_id: TransactionId::new(1),
+        amount: NonNegativeDecimal::try_from(10).unwrap(),
+    };
+    let withdrawal = Withdrawal {
+        client_id,
+        tx_id: TransactionId::new(2),
+        amount: NonNegativeDecimal::try_from(15).unwrap(),
+    };
+
+    let txs = [
+        TxRecord::from(deposit),
+        // fails due to insufficient funds
+        TxRecord::from(withdrawal),
+        // replay
+        TxRecord::from(deposit),
+        TxRecord::from(Deposit {
+            tx_id: TransactionId::new(3),
+            ..deposit
+        }),
+        // retry of the failed withdrawal
+        TxRecord::from(withdrawal),
+    ];
+
+    // act
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
+
+    // assert
+    let account = assert_some!(assert_ok!(accounts.get(client_id).await));
+    assert_eq!(account.available, dec!(5), "unexpected available amount");
+    assert_eq!(account.total, dec!(5), "unexpected total amount");
+
+    let tx = assert_some!(transactions.get(withdrawal.tx_id).await);
+    assert_eq!(
+        tx.status,
+        TransactionStatus::Processed,
+        "Unexpected tx_status"
+    );
+
+    assert_eq!(seen_index.len(), 3, "Unexpected number of seen tx ids");
+}
+
+#[tokio::test]
+async fn bitmap_index_rejects_replays_and_allows_retries() {
+    process_with_index(Arc::new(BitmapSeenIndex::new())).await;
+}
+
+#[tokio::test]
+async fn roaring_index_rejects_replays_and_allows_retries() {
+    process_with_index(Arc::new(RoaringSeenIndex::new())).await;
+}
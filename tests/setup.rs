@@ -1,6 +1,12 @@
+// Shared by all integration tests, not every test uses every component
+#![allow(dead_code)]
+
 use std::sync::Arc;
 
-use toy_payment_engine::prelude::{InMemoryAccountRepository, InMemoryTxRepository, PaymentEngine};
+use toy_payment_engine::prelude::{
+    EngineConfig, InMemoryAccountRepository, InMemoryJournal, InMemoryLedger, InMemoryTxRepository,
+    JournalRepository, LedgerRepository, PaymentEngine,
+};
 
 pub struct Components {
     pub engine: PaymentEngine<InMemoryAccountRepository, InMemoryTxRepository>,
@@ -10,12 +16,56 @@ pub struct Components {
 
 impl Components {
     pub fn setup() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::builder().config(config).build()
+    }
+
+    /// Attaches optional extensions to the engine, e.g. `Components::builder().ledger(&ledger).build()`
+    pub fn builder() -> ComponentsBuilder {
+        ComponentsBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct ComponentsBuilder {
+    config: EngineConfig,
+    ledger: Option<Arc<InMemoryLedger>>,
+    journal: Option<Arc<InMemoryJournal>>,
+}
+
+impl ComponentsBuilder {
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn ledger(mut self, ledger: &Arc<InMemoryLedger>) -> Self {
+        self.ledger = Some(Arc::clone(ledger));
+        self
+    }
+
+    pub fn journal(mut self, journal: &Arc<InMemoryJournal>) -> Self {
+        self.journal = Some(Arc::clone(journal));
+        self
+    }
+
+    pub fn build(self) -> Components {
         let accounts = Arc::new(InMemoryAccountRepository::new());
         let transactions = Arc::new(InMemoryTxRepository::new());
-        let engine = PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions));
+        let mut builder = PaymentEngine::builder(Arc::clone(&accounts), Arc::clone(&transactions))
+            .config(self.config);
+        if let Some(ledger) = self.ledger {
+            builder = builder.ledger(ledger as Arc<dyn LedgerRepository>);
+        }
+        if let Some(journal) = self.journal {
+            builder = builder.journal(journal as Arc<dyn JournalRepository>);
+        }
 
-        Self {
-            engine,
+        Components {
+            engine: builder.build(),
             transactions,
             accounts,
         }
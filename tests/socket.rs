@@ -0,0 +1,159 @@
+use std::num::NonZeroUsize;
+
+use claims::{assert_ok, assert_some};
+use rust_decimal::dec;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use setup::Components;
+use toy_payment_engine::models::client::ClientId;
+use toy_payment_engine::prelude::{
+    AccountRepository, Ingest, LineFormat, serve_connection, serve_tcp,
+};
+
+mod setup;
+
+fn capacity(capacity: usize) -> NonZeroUsize {
+    NonZeroUsize::new(capacity).unwrap()
+}
+
+/// Writes the lines and returns the acks, after the producer finished sending
+async fn produce<S>(stream: S, lines: &str) -> Vec<Value>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    assert_ok!(writer.write_all(lines.as_bytes()).await);
+    assert_ok!(writer.shutdown().await);
+
+    let mut acks = Vec::new();
+    let mut reader = BufReader::new(reader).lines();
+    while let Some(line) = assert_ok!(reader.next_line().await) {
+        acks.push(assert_ok!(serde_json::from_str(&line)));
+    }
+
+    acks
+}
+
+#[tokio::test]
+async fn every_csv_line_is_acked_in_order() {
+    let Components { engine, .. } = Components::setup();
+    let (ingest, _) = Ingest::spawn(engine, capacity(8));
+    let (producer, server) = tokio::io::duplex(1024);
+
+    // arrange
+    let lines = "type,client,tx,amount\n\
+                 deposit, 1, 1, 10\n\
+                 \n\
+                 withdrawal, 1, 2, 20\n\
+                 refund, 1, 3\n\
+                 dispute, 1, 1\n";
+
+    // act
+    let server = tokio::spawn(serve_connection(server, ingest, LineFormat::Csv));
+    let acks = produce(producer, lines).await;
+
+    // assert
+    assert_ok!(assert_ok!(server.await));
+    let outcomes: Vec<_> = acks
+        .iter()
+        .map(|ack| (ack["line"].clone(), ack["outcome"].clone()))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            (2.into(), "applied".into()),
+            (4.into(), "rejected".into()),
+            (5.into(), "malformed".into()),
+            (6.into(), "applied".into()),
+        ]
+    );
+    assert_eq!(acks[1]["rejection"]["reason"], "insufficient_funds");
+    assert_eq!(acks[3]["account"]["held"], "10");
+}
+
+#[tokio::test]
+async fn concurrent_tcp_producers_are_merged() {
+    let Components {
+        engine, accounts, ..
+    } = Components::setup();
+    let (ingest, _) = Ingest::spawn(engine, capacity(4));
+    let listener = assert_ok!(TcpListener::bind("127.0.0.1:0").await);
+    let addr = assert_ok!(listener.local_addr());
+    tokio::spawn(serve_tcp(listener, ingest, LineFormat::Json));
+
+    // act
+    let producers: Vec<_> = (0..4u32)
+        .map(|producer| {
+            tokio::spawn(async move {
+                let lines: String = (0..25u32)
+                    .map(|idx| {
+                        let tx = producer * 100 + idx;
+                        format!(r#"{{"type":"deposit","client":1,"tx":{tx},"amount":"1"}}"#) + "\n"
+                    })
+                    .collect();
+                let stream = assert_ok!(TcpStream::connect(addr).await);
+
+                produce(stream, &lines).await
+            })
+        })
+        .collect();
+
+    // assert
+    for producer in producers {
+        let acks = assert_ok!(producer.await);
+        assert_eq!(acks.len(), 25, "Expected an ack per record");
+        assert!(
+            acks.iter().all(|ack| ack["outcome"] == "applied"),
+            "Expected every record to be applied"
+        );
+    }
+
+    let account = assert_some!(assert_ok!(accounts.get(ClientId::new(1)).await));
+    assert_eq!(account.total, dec!(100));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_producer_is_acked() {
+    use tokio::net::{UnixListener, UnixStream};
+    use toy_payment_engine::prelude::serve_unix;
+
+    let Components { engine, .. } = Components::setup();
+    let (ingest, _) = Ingest::spawn(engine, capacity(4));
+    let path = std::env::temp_dir().join(format!("payment-engine-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = assert_ok!(UnixListener::bind(&path));
+    tokio::spawn(serve_unix(listener, ingest, LineFormat::Csv));
+
+    // act
+    let stream = assert_ok!(UnixStream::connect(&path).await);
+    let acks = produce(stream, "deposit,1,1,5\n").await;
+
+    // assert
+    assert_eq!(acks.len(), 1);
+    assert_eq!(acks[0]["account"]["available"], "5");
+    assert_ok!(std::fs::remove_file(&path));
+}
+
+#[tokio::test]
+async fn engine_is_returned_after_the_last_producer() {
+    let Components { engine, .. } = Components::setup();
+    let (ingest, task) = Ingest::spawn(engine, capacity(1));
+
+    // arrange
+    let record = assert_ok!(serde_json::from_str(
+        r#"{"type":"deposit","client":2,"tx":1,"amount":"3"}"#
+    ));
+    assert_ok!(ingest.submit(record).await);
+
+    // act
+    drop(ingest);
+    let engine = assert_ok!(task.await);
+
+    // assert
+    let account = assert_some!(assert_ok!(engine.accounts().get(ClientId::new(2)).await));
+    assert_eq!(account.total, dec!(3));
+}
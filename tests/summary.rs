@@ -0,0 +1,154 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use claims::{assert_err, assert_ok, assert_some};
+use rust_decimal::dec;
+
+use toy_payment_engine::prelude::{
+    Abort, CsvDecoder, DecodeStats, EngineConfig, FailureMode, InMemoryAccountRepository,
+    InMemoryTxRepository, PaymentEngine, RecordCounts, RunStats,
+};
+
+fn engine_with_run_stats() -> PaymentEngine<InMemoryAccountRepository, InMemoryTxRepository> {
+    PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    )
+    .run_stats(Arc::new(RunStats::new()))
+    .build()
+}
+
+#[tokio::test]
+async fn batch_run_is_summarized() {
+    // arrange
+    let engine = engine_with_run_stats();
+    let csv = "type,client,tx,amount
+deposit,1,1,10
+unknown,1,2,1
+withdrawal,1,3,4
+withdrawal,1,4,100
+deposit,2,5,3
+dispute,2,5,
+resolve,2,5,
+chargeback,2,5,
+deposit,3,6,1
+dispute,3,6,
+";
+    let mut decoder = CsvDecoder::new(Cursor::new(csv));
+
+    // act
+    assert_ok!(engine.process_numbered(decoder.decode_numbered_tx()).await);
+    let summary = assert_some!(assert_ok!(engine.run_summary(decoder.stats()).await));
+
+    // assert
+    assert_eq!(
+        summary.records,
+        RecordCounts {
+            read: 10,
+            decoded: 9,
+            applied: 8,
+            rejected: 1,
+            failed: 0,
+        }
+    );
+    assert_eq!(summary.rejections["insufficient_funds"], 1);
+    assert_eq!(summary.types["deposit"].count, 3);
+    assert_eq!(summary.types["deposit"].amount, dec!(14));
+    assert_eq!(summary.types["withdrawal"].count, 2);
+    assert_eq!(summary.types["withdrawal"].amount, dec!(4));
+    assert_eq!(summary.types["chargeback"].applied, 1);
+    assert_eq!(summary.accounts_created, 3);
+    assert_eq!(summary.accounts_locked, 1);
+    assert_eq!(summary.open_disputes, 1);
+}
+
+#[tokio::test]
+async fn accounts_existing_before_the_run_are_not_counted_as_created() {
+    // arrange
+    let accounts = Arc::new(InMemoryAccountRepository::new());
+    let transactions = Arc::new(InMemoryTxRepository::new());
+    let existing = "type,client,tx,amount\ndeposit,1,1,10\n";
+    assert_ok!(
+        PaymentEngine::new(Arc::clone(&accounts), Arc::clone(&transactions))
+            .process(CsvDecoder::new(Cursor::new(existing)).decode_tx())
+            .await
+    );
+    let engine = PaymentEngine::builder(accounts, transactions)
+        .run_stats(Arc::new(RunStats::new()))
+        .build();
+    let csv = "type,client,tx,amount\ndeposit,1,2,5\ndeposit,2,3,5\n";
+    let mut decoder = CsvDecoder::new(Cursor::new(csv));
+
+    // act
+    assert_ok!(engine.process(decoder.decode_tx()).await);
+    let summary = assert_some!(assert_ok!(engine.run_summary(decoder.stats()).await));
+
+    // assert
+    assert_eq!(summary.accounts_created, 1);
+    assert_eq!(
+        summary.records,
+        RecordCounts {
+            read: 2,
+            decoded: 2,
+            applied: 2,
+            rejected: 0,
+            failed: 0,
+        }
+    );
+}
+
+#[tokio::test]
+async fn strict_run_is_summarized_up_to_the_aborting_record() {
+    // arrange
+    let engine = PaymentEngine::builder(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    )
+    .config(EngineConfig {
+        failure_mode: FailureMode::Strict,
+        ..EngineConfig::default()
+    })
+    .run_stats(Arc::new(RunStats::new()))
+    .build();
+    let csv = "type,client,tx,amount
+deposit,1,1,10
+withdrawal,1,2,100
+deposit,1,3,5
+";
+    let mut decoder = CsvDecoder::new(Cursor::new(csv));
+
+    // act
+    assert_err!(engine.process_numbered(decoder.decode_numbered_tx()).await);
+    let summary = assert_some!(assert_ok!(engine.run_summary(decoder.stats()).await));
+
+    // assert
+    assert_eq!(
+        summary.records,
+        RecordCounts {
+            read: 2,
+            decoded: 2,
+            applied: 1,
+            rejected: 1,
+            failed: 0,
+        }
+    );
+    let Abort { line, error } = assert_some!(summary.aborted);
+    assert_eq!(line, Some(3));
+    assert!(
+        error.contains("No sufficient funds"),
+        "Unexpected error: {error}"
+    );
+}
+
+#[tokio::test]
+async fn engine_without_run_stats_has_no_summary() {
+    let engine = PaymentEngine::new(
+        Arc::new(InMemoryAccountRepository::new()),
+        Arc::new(InMemoryTxRepository::new()),
+    );
+
+    assert_eq!(
+        assert_ok!(engine.run_summary(DecodeStats::default()).await),
+        None
+    );
+}
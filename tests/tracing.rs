@@ -0,0 +1,74 @@
+use std::io::{self, Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use claims::assert_ok;
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+use setup::Components;
+use toy_payment_engine::prelude::CsvDecoder;
+
+mod setup;
+
+/// Collects the log lines in memory
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<Value> {
+        let buffer = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buffer)
+            .lines()
+            .map(|line| assert_ok!(serde_json::from_str(line)))
+            .collect()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn failed_record_is_logged_within_its_record_span() {
+    // arrange
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(capture.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let Components { engine, .. } = Components::setup();
+    let csv = "type,client,tx,amount\ndeposit,1,1,10\nwithdrawal,1,2,20\n";
+
+    // act
+    assert_ok!(
+        engine
+            .process_numbered(CsvDecoder::new(Cursor::new(csv)).decode_numbered_tx())
+            .await
+    );
+
+    // assert
+    let lines = capture.lines();
+    assert_eq!(lines.len(), 1);
+    let span = &lines[0]["span"];
+    assert_eq!(span["name"], "record");
+    assert_eq!(span["type"], "withdrawal");
+    assert_eq!(span["client"], 1);
+    assert_eq!(span["tx"], 2);
+    assert_eq!(span["line"], 3);
+}
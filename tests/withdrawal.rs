@@ -41,7 +41,7 @@ async fn client_can_perform_withdrawal_if_sufficient_funds() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;
@@ -107,7 +107,7 @@ async fn client_cant_perform_withdrawal_if_insufficient_funds() {
     .into_iter();
 
     // act
-    engine.process(stream::iter(txs).fuse()).await;
+    assert_ok!(engine.process(stream::iter(txs).fuse()).await);
 
     // assert
     let account = accounts.get(client_id).await;